@@ -0,0 +1,429 @@
+use ::std::collections::{BTreeMap, HashMap};
+use ::std::net::IpAddr;
+
+use ::chrono::prelude::*;
+use ::tui::backend::Backend;
+use ::tui::layout::{Constraint, Direction, Rect};
+use ::tui::style::{Color, Modifier, Style};
+use ::tui::terminal::Frame;
+use ::tui::widgets::{Block, Borders, Paragraph, Sparkline, Text, Widget};
+
+use crate::display::{
+    group_processes_by_name, Bandwidth, ContainerKey, DisplayBandwidth, DisplayBytes,
+    InterfaceData, NetworkData, ProcessKey, RowKey, UIState,
+};
+use crate::network::display_connection_string;
+use crate::ProcessInfo;
+
+pub struct DetailPane {
+    title: &'static str,
+    fields: Vec<(&'static str, String)>,
+    upload_history: Vec<u64>,
+    download_history: Vec<u64>,
+}
+
+fn display_rate(bandwidth: Option<&impl Bandwidth>) -> String {
+    match bandwidth {
+        Some(bandwidth) => format!(
+            "{} / {}",
+            DisplayBandwidth(bandwidth.get_total_bytes_uploaded() as f64),
+            DisplayBandwidth(bandwidth.get_total_bytes_downloaded() as f64)
+        ),
+        None => String::from("-"),
+    }
+}
+
+fn display_total(bandwidth: Option<&impl Bandwidth>) -> String {
+    match bandwidth {
+        Some(bandwidth) => format!(
+            "{} / {}",
+            DisplayBytes(bandwidth.get_total_bytes_uploaded() as f64),
+            DisplayBytes(bandwidth.get_total_bytes_downloaded() as f64)
+        ),
+        None => String::from("-"),
+    }
+}
+
+fn display_pid(process: &ProcessKey) -> String {
+    process
+        .pid
+        .map(|pid| pid.to_string())
+        .unwrap_or_else(|| String::from("-"))
+}
+
+// the full id, the short one is what the tables have room for
+fn display_container(container: &ContainerKey) -> String {
+    match container {
+        ContainerKey::Container(container) => container.id.clone(),
+        _ => container.short_name(),
+    }
+}
+
+fn display_host(ip: &IpAddr, ip_to_host: &HashMap<IpAddr, String>) -> String {
+    ip_to_host
+        .get(ip)
+        .cloned()
+        .unwrap_or_else(|| String::from("-"))
+}
+
+// a row grouped by name adds up all the processes of that name
+fn data_for_process(
+    processes: &BTreeMap<ProcessKey, NetworkData>,
+    process: &ProcessKey,
+) -> Option<NetworkData> {
+    if process.pid.is_some() {
+        processes.get(process).cloned()
+    } else {
+        group_processes_by_name(processes).remove(process)
+    }
+}
+
+impl DetailPane {
+    pub fn new(key: &RowKey, state: &UIState, ip_to_host: &HashMap<IpAddr, String>) -> Self {
+        let (title, mut fields) = match key {
+            RowKey::Process(process) => {
+                let data = data_for_process(&state.processes, process);
+                let cumulative_data = data_for_process(&state.cumulative_processes, process);
+                let process_info = state.get_process_info(process);
+                let describe = |attribute: fn(&ProcessInfo) -> &String| {
+                    process_info
+                        .map(attribute)
+                        .filter(|value| !value.is_empty())
+                        .cloned()
+                        .unwrap_or_else(|| String::from("-"))
+                };
+                (
+                    "Process details",
+                    vec![
+                        ("Process", process.name.clone()),
+                        ("PID", display_pid(process)),
+                        (
+                            "Parent",
+                            process_info
+                                .and_then(|process_info| process_info.ancestors.first())
+                                .map(|(pid, name)| format!("{} ({})", name, pid))
+                                .unwrap_or_else(|| String::from("-")),
+                        ),
+                        ("User", describe(|process_info| &process_info.user)),
+                        (
+                            "Command line",
+                            describe(|process_info| &process_info.cmdline),
+                        ),
+                        ("Executable", describe(|process_info| &process_info.exe)),
+                        ("Cgroup", describe(|process_info| &process_info.cgroup)),
+                        ("Systemd unit", describe(|process_info| &process_info.unit)),
+                        (
+                            "Container",
+                            process_info
+                                .and_then(|process_info| process_info.container.as_ref())
+                                .map(|container| container.id.clone())
+                                .unwrap_or_else(|| String::from("-")),
+                        ),
+                        (
+                            "Connections",
+                            cumulative_data
+                                .as_ref()
+                                .map(|data| data.connection_count.to_string())
+                                .unwrap_or_else(|| String::from("-")),
+                        ),
+                        ("Rate Up / Down", display_rate(data.as_ref())),
+                        ("Total Up / Down", display_total(cumulative_data.as_ref())),
+                    ],
+                )
+            }
+            RowKey::User(user) => (
+                "User details",
+                vec![
+                    ("User", user.clone()),
+                    (
+                        "Connections",
+                        state
+                            .cumulative_users
+                            .get(user)
+                            .map(|data| data.connection_count.to_string())
+                            .unwrap_or_else(|| String::from("-")),
+                    ),
+                    ("Rate Up / Down", display_rate(state.users.get(user))),
+                    (
+                        "Total Up / Down",
+                        display_total(state.cumulative_users.get(user)),
+                    ),
+                ],
+            ),
+            RowKey::Container(container) => (
+                "Container details",
+                vec![
+                    ("Container", display_container(container)),
+                    (
+                        "Pod UID",
+                        match container {
+                            ContainerKey::Container(container) => container.pod_uid.clone(),
+                            _ => None,
+                        }
+                        .unwrap_or_else(|| String::from("-")),
+                    ),
+                    (
+                        "Connections",
+                        state
+                            .cumulative_containers
+                            .get(container)
+                            .map(|data| data.connection_count.to_string())
+                            .unwrap_or_else(|| String::from("-")),
+                    ),
+                    (
+                        "Rate Up / Down",
+                        display_rate(state.containers.get(container)),
+                    ),
+                    (
+                        "Total Up / Down",
+                        display_total(state.cumulative_containers.get(container)),
+                    ),
+                ],
+            ),
+            RowKey::Unit(unit) => (
+                "Systemd unit details",
+                vec![
+                    ("Unit", unit.clone()),
+                    (
+                        "Connections",
+                        state
+                            .cumulative_units
+                            .get(unit)
+                            .map(|data| data.connection_count.to_string())
+                            .unwrap_or_else(|| String::from("-")),
+                    ),
+                    ("Rate Up / Down", display_rate(state.units.get(unit))),
+                    (
+                        "Total Up / Down",
+                        display_total(state.cumulative_units.get(unit)),
+                    ),
+                ],
+            ),
+            RowKey::Interface(interface_name) => {
+                let interface_data = state
+                    .interfaces
+                    .get(interface_name)
+                    .filter(|data| data.counted);
+                let cumulative_interface_data = state
+                    .cumulative_interfaces
+                    .get(interface_name)
+                    .filter(|data| data.counted);
+                let sniffed = |interface_data: Option<&InterfaceData>| {
+                    interface_data.map(|interface_data| interface_data.sniffed.clone())
+                };
+                (
+                    "Interface details",
+                    vec![
+                        ("Interface", interface_name.clone()),
+                        (
+                            "Link speed",
+                            interface_data
+                                .and_then(|data| data.speed)
+                                .map(|speed| format!("{} Mbit/s", speed))
+                                .unwrap_or_else(|| String::from("-")),
+                        ),
+                        (
+                            "Link utilization",
+                            interface_data
+                                .and_then(|data| data.link_utilization())
+                                .map(|percentage| format!("{:.1}%", percentage))
+                                .unwrap_or_else(|| String::from("-")),
+                        ),
+                        (
+                            "Packets per second",
+                            interface_data
+                                .map(|data| data.packets.to_string())
+                                .unwrap_or_else(|| String::from("-")),
+                        ),
+                        (
+                            "Errors / Drops",
+                            interface_data
+                                .map(|data| format!("{} / {}", data.errors, data.drops))
+                                .unwrap_or_else(|| String::from("-")),
+                        ),
+                        ("Rate Up / Down", display_rate(interface_data)),
+                        ("Total Up / Down", display_total(cumulative_interface_data)),
+                        (
+                            "Sniffed connections",
+                            sniffed(state.cumulative_interfaces.get(interface_name))
+                                .map(|data| data.connection_count.to_string())
+                                .unwrap_or_else(|| String::from("-")),
+                        ),
+                        (
+                            "Sniffed rate Up / Down",
+                            display_rate(sniffed(state.interfaces.get(interface_name)).as_ref()),
+                        ),
+                        (
+                            "Sniffed total Up / Down",
+                            display_total(
+                                sniffed(state.cumulative_interfaces.get(interface_name)).as_ref(),
+                            ),
+                        ),
+                    ],
+                )
+            }
+            RowKey::RemoteAddress(ip) => (
+                "Remote address details",
+                vec![
+                    ("Remote address", ip.to_string()),
+                    ("Remote host", display_host(ip, ip_to_host)),
+                    (
+                        "Connections",
+                        state
+                            .cumulative_remote_addresses
+                            .get(ip)
+                            .map(|data| data.connection_count.to_string())
+                            .unwrap_or_else(|| String::from("-")),
+                    ),
+                    (
+                        "Rate Up / Down",
+                        display_rate(state.remote_addresses.get(ip)),
+                    ),
+                    (
+                        "Total Up / Down",
+                        display_total(state.cumulative_remote_addresses.get(ip)),
+                    ),
+                ],
+            ),
+            RowKey::Connection(connection) => {
+                let connection_data = state.cumulative_connections.get(connection);
+                let interface_name = connection_data
+                    .map(|data| data.interface_name.clone())
+                    .unwrap_or_default();
+                let process = connection_data
+                    .map(|data| data.process.clone())
+                    .unwrap_or_default();
+                let (process_name, container) = if process.name.is_empty() {
+                    (String::from("-"), String::from("-"))
+                } else {
+                    (
+                        process.name.clone(),
+                        connection_data
+                            .map(|data| display_container(&data.container))
+                            .unwrap_or_default(),
+                    )
+                };
+                (
+                    "Connection details",
+                    vec![
+                        (
+                            "Connection",
+                            display_connection_string(connection, ip_to_host, &interface_name),
+                        ),
+                        (
+                            "Local address",
+                            format!(
+                                "{}:{}",
+                                connection.local_socket.ip, connection.local_socket.port
+                            ),
+                        ),
+                        (
+                            "Remote address",
+                            format!(
+                                "{}:{}",
+                                connection.remote_socket.ip, connection.remote_socket.port
+                            ),
+                        ),
+                        (
+                            "Remote host",
+                            display_host(&connection.remote_socket.ip, ip_to_host),
+                        ),
+                        ("Interface", interface_name),
+                        ("Protocol", connection.local_socket.protocol.to_string()),
+                        ("Process", process_name),
+                        ("PID", display_pid(&process)),
+                        ("Container", container),
+                        (
+                            "Rate Up / Down",
+                            display_rate(state.connections.get(connection)),
+                        ),
+                        ("Total Up / Down", display_total(connection_data)),
+                    ],
+                )
+            }
+        };
+        let history = state.history.get(key);
+        let first_seen = history
+            .map(|history| {
+                Local
+                    .timestamp(history.first_seen, 0)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|| String::from("-"));
+        fields.push(("First seen", first_seen));
+        let (upload_history, download_history) = history
+            .map(|history| {
+                history
+                    .bytes
+                    .iter()
+                    .map(|(uploaded, downloaded)| (*uploaded as u64, *downloaded as u64))
+                    .unzip()
+            })
+            .unwrap_or_default();
+        DetailPane {
+            title,
+            fields,
+            upload_history,
+            download_history,
+        }
+    }
+    pub fn render(&self, frame: &mut Frame<impl Backend>, rect: Rect) {
+        let mut block = Block::default()
+            .title(self.title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow));
+        block.render(frame, rect);
+        let parts = ::tui::layout::Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints(
+                [
+                    Constraint::Length(self.fields.len() as u16 + 1),
+                    Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(rect);
+        let charts = ::tui::layout::Layout::default()
+            .direction(Direction::Vertical)
+            .margin(0)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(parts[1]);
+
+        let label_width = self
+            .fields
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        let text: Vec<Text> = self
+            .fields
+            .iter()
+            .flat_map(|(label, value)| {
+                vec![
+                    Text::styled(
+                        format!(" {:width$}  ", label, width = label_width),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    Text::raw(format!("{}\n", value)),
+                ]
+            })
+            .collect();
+        Paragraph::new(text.iter()).render(frame, parts[0]);
+
+        for (rect, title, history) in &[
+            (charts[0], "Upload history", &self.upload_history),
+            (charts[1], "Download history", &self.download_history),
+        ] {
+            // the most recent updates are shown, as many as fit
+            let width = rect.width.saturating_sub(2) as usize;
+            let history = &history[history.len().saturating_sub(width)..];
+            Sparkline::default()
+                .block(Block::default().title(title).borders(Borders::ALL))
+                .style(Style::default().fg(Color::Green).modifier(Modifier::BOLD))
+                .data(history)
+                .render(frame, *rect);
+        }
+    }
+}
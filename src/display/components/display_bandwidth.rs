@@ -2,16 +2,28 @@ use ::std::fmt;
 
 pub struct DisplayBandwidth(pub f64);
 
+pub struct DisplayBytes(pub f64);
+
+fn format_bytes(f: &mut fmt::Formatter<'_>, bytes: f64, suffix: &str) -> fmt::Result {
+    if bytes > 999_999_999.0 {
+        write!(f, "{:.2}GB{}", bytes / 1_000_000_000.0, suffix)
+    } else if bytes > 999_999.0 {
+        write!(f, "{:.2}MB{}", bytes / 1_000_000.0, suffix)
+    } else if bytes > 999.0 {
+        write!(f, "{:.2}KB{}", bytes / 1000.0, suffix)
+    } else {
+        write!(f, "{}B{}", bytes, suffix)
+    }
+}
+
 impl fmt::Display for DisplayBandwidth {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.0 > 999_999_999.0 {
-            write!(f, "{:.2}GBps", self.0 / 1_000_000_000.0)
-        } else if self.0 > 999_999.0 {
-            write!(f, "{:.2}MBps", self.0 / 1_000_000.0)
-        } else if self.0 > 999.0 {
-            write!(f, "{:.2}KBps", self.0 / 1000.0)
-        } else {
-            write!(f, "{}Bps", self.0)
-        }
+        format_bytes(f, self.0, "ps")
+    }
+}
+
+impl fmt::Display for DisplayBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_bytes(f, self.0, "")
     }
 }
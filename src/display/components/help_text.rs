@@ -6,18 +6,34 @@ use ::tui::widgets::{Paragraph, Text, Widget};
 
 pub struct HelpText {
     pub paused: bool,
+    pub filter: Option<String>,
+    pub filter_prompt: Option<String>,
 }
 
-const TEXT_WHEN_PAUSED: &str = " Press <SPACE> to resume.";
-const TEXT_WHEN_NOT_PAUSED: &str = " Press <SPACE> to pause.";
+const TEXT_WHEN_PAUSED: &str =
+    " Press <SPACE> to resume, <t> to toggle totals, <s> or <b/u/d/+/c/n> to sort, <TAB> to select a table, </> to filter, <g> to group processes by name, <p> for a process tree.";
+const TEXT_WHEN_NOT_PAUSED: &str =
+    " Press <SPACE> to pause, <t> to toggle totals, <s> or <b/u/d/+/c/n> to sort, <TAB> to select a table, </> to filter, <g> to group processes by name, <p> for a process tree.";
 
 impl HelpText {
     pub fn render(&self, frame: &mut Frame<impl Backend>, rect: Rect) {
         let text = {
-            let content = if self.paused {
-                TEXT_WHEN_PAUSED
+            let content = if let Some(filter_prompt) = &self.filter_prompt {
+                // like less, the pattern is typed in after a slash
+                format!(" /{}", filter_prompt)
             } else {
-                TEXT_WHEN_NOT_PAUSED
+                let content = if self.paused {
+                    TEXT_WHEN_PAUSED
+                } else {
+                    TEXT_WHEN_NOT_PAUSED
+                };
+                // the active filter comes first, so narrow terminals do not cut it off
+                match &self.filter {
+                    Some(filter) => {
+                        format!(" Filter: {}, <f> to filter the totals.{}", filter, content)
+                    }
+                    None => content.to_string(),
+                }
             };
 
             [Text::styled(
@@ -2,6 +2,7 @@ use ::tui::backend::Backend;
 use ::tui::layout::{Constraint, Direction, Rect};
 use ::tui::terminal::Frame;
 
+use super::DetailPane;
 use super::HelpText;
 use super::Table;
 use super::TotalBandwidth;
@@ -28,6 +29,7 @@ fn top_app_and_bottom_split(rect: Rect) -> (Rect, Rect, Rect) {
 pub struct Layout<'a> {
     pub header: TotalBandwidth<'a>,
     pub children: Vec<Table<'a>>,
+    pub detail: Option<DetailPane>,
     pub footer: HelpText,
 }
 
@@ -89,27 +91,95 @@ impl<'a> Layout<'a> {
         }
     }
 
+    fn build_four_children_layout(&self, rect: Rect) -> Vec<Rect> {
+        if rect.height < FIRST_HEIGHT_BREAKPOINT || rect.width < FIRST_WIDTH_BREAKPOINT {
+            // if the space is not enough, we drop the fourth element and carry on as with three
+            self.build_three_children_layout(rect)
+        } else {
+            // the bottom half is shared with the fourth element, which gets the smaller part
+            let halves = ::tui::layout::Layout::default()
+                .direction(Direction::Vertical)
+                .margin(0)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                .split(rect);
+            let top_quarters = ::tui::layout::Layout::default()
+                .direction(Direction::Horizontal)
+                .margin(0)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                .split(halves[0]);
+            let bottom_parts = ::tui::layout::Layout::default()
+                .direction(Direction::Horizontal)
+                .margin(0)
+                .constraints([Constraint::Percentage(66), Constraint::Percentage(34)].as_ref())
+                .split(halves[1]);
+
+            vec![
+                top_quarters[0],
+                top_quarters[1],
+                bottom_parts[0],
+                bottom_parts[1],
+            ]
+        }
+    }
+
+    fn build_many_children_layout(&self, rect: Rect) -> Vec<Rect> {
+        if rect.height < FIRST_HEIGHT_BREAKPOINT || rect.width < FIRST_WIDTH_BREAKPOINT {
+            // if the space is not enough, we drop the elements past the fourth
+            self.build_four_children_layout(rect)
+        } else {
+            // two elements to a row, the last one has a row of its own when there is an odd number
+            let row_count = (self.children.len() + 1) / 2;
+            let rows = ::tui::layout::Layout::default()
+                .direction(Direction::Vertical)
+                .margin(0)
+                .constraints(vec![Constraint::Ratio(1, row_count as u32); row_count].as_ref())
+                .split(rect);
+            let mut slots = Vec::new();
+            for (index, row) in rows.into_iter().enumerate() {
+                if index * 2 + 1 == self.children.len() {
+                    slots.push(row);
+                } else {
+                    slots.extend(self.progressive_split(row, vec![Direction::Horizontal]));
+                }
+            }
+            slots
+        }
+    }
+
     fn build_layout(&self, rect: Rect) -> Vec<Rect> {
         if self.children.len() == 1 {
             // if there's only one element to render, it can take the whole frame
             vec![rect]
         } else if self.children.len() == 2 {
             self.build_two_children_layout(rect)
-        } else {
+        } else if self.children.len() == 3 {
             self.build_three_children_layout(rect)
+        } else if self.children.len() == 4 {
+            self.build_four_children_layout(rect)
+        } else {
+            self.build_many_children_layout(rect)
         }
     }
-    pub fn render(&self, frame: &mut Frame<impl Backend>, rect: Rect) {
+    // returns the page size of each table that was rendered, tables that do not fit are left out
+    // and none are rendered while the detail pane is open
+    pub fn render(&self, frame: &mut Frame<impl Backend>, rect: Rect) -> Vec<usize> {
         let (top, app, bottom) = top_app_and_bottom_split(rect);
-        let layout_slots = self.build_layout(app);
-        for i in 0..layout_slots.len() {
-            if let Some(rect) = layout_slots.get(i) {
-                if let Some(child) = self.children.get(i) {
-                    child.render(frame, *rect);
+        let mut page_sizes = Vec::new();
+        if let Some(detail) = &self.detail {
+            // the detail pane covers the tables entirely
+            detail.render(frame, app);
+        } else {
+            let layout_slots = self.build_layout(app);
+            for i in 0..layout_slots.len() {
+                if let Some(rect) = layout_slots.get(i) {
+                    if let Some(child) = self.children.get(i) {
+                        page_sizes.push(child.render(frame, *rect));
+                    }
                 }
             }
         }
         self.header.render(frame, top);
         self.footer.render(frame, bottom);
+        page_sizes
     }
 }
@@ -1,9 +1,11 @@
+mod detail_pane;
 mod display_bandwidth;
 mod help_text;
 mod layout;
 mod table;
 mod total_bandwidth;
 
+pub use detail_pane::*;
 pub use display_bandwidth::*;
 pub use help_text::*;
 pub use layout::*;
@@ -1,48 +1,114 @@
-use ::std::collections::{BTreeMap, HashMap};
+use ::std::collections::{BTreeMap, HashMap, HashSet};
+use ::std::iter;
 
 use ::tui::backend::Backend;
 use ::tui::layout::Rect;
-use ::tui::style::{Color, Style};
+use ::tui::style::{Color, Modifier, Style};
 use ::tui::terminal::Frame;
 use ::tui::widgets::{Block, Borders, Row, Widget};
 
-use crate::display::{Bandwidth, DisplayBandwidth, UIState};
+use crate::display::{
+    group_processes_by_name, sort_connections, sort_containers, sort_interfaces, sort_processes,
+    sort_remote_addresses, sort_units, sort_users, Bandwidth, ContainerKey, DisplayBandwidth,
+    DisplayBytes, NetworkData, ProcessKey, ProcessTree, RowKey, SortBy, UIState,
+};
 use crate::network::{display_connection_string, display_ip_or_host};
 
 use ::std::net::IpAddr;
-use std::iter::FromIterator;
 
-fn display_upload_and_download(bandwidth: &impl Bandwidth) -> String {
-    format!(
-        "{} / {}",
-        DisplayBandwidth(bandwidth.get_total_bytes_uploaded() as f64),
-        DisplayBandwidth(bandwidth.get_total_bytes_downloaded() as f64)
-    )
+fn display_upload_and_download(bandwidth: &impl Bandwidth, show_totals: bool) -> String {
+    let uploaded = bandwidth.get_total_bytes_uploaded() as f64;
+    let downloaded = bandwidth.get_total_bytes_downloaded() as f64;
+    if show_totals {
+        format!("{} / {}", DisplayBytes(uploaded), DisplayBytes(downloaded))
+    } else {
+        format!(
+            "{} / {}",
+            DisplayBandwidth(uploaded),
+            DisplayBandwidth(downloaded)
+        )
+    }
 }
 
-fn sort_by_bandwidth<'a, T>(
-    list: &'a mut Vec<(T, &impl Bandwidth)>,
-) -> &'a Vec<(T, &'a impl Bandwidth)> {
-    list.sort_by(|(_, a), (_, b)| {
-        let a_highest = if a.get_total_bytes_downloaded() > a.get_total_bytes_uploaded() {
-            a.get_total_bytes_downloaded()
-        } else {
-            a.get_total_bytes_uploaded()
+// the default order, by the higher of up and down, marks both
+fn bandwidth_column_name(show_totals: bool, sort_by: SortBy) -> &'static str {
+    match (show_totals, sort_by) {
+        (false, SortBy::Bandwidth) => "Rate Up▼ / Down▼",
+        (false, SortBy::Upload) => "Rate Up▼ / Down",
+        (false, SortBy::Download) => "Rate Up / Down▼",
+        (false, SortBy::Total) => "Rate Up + Down▼",
+        (false, _) => "Rate Up / Down",
+        (true, SortBy::Bandwidth) => "Total Up▼ / Down▼",
+        (true, SortBy::Upload) => "Total Up▼ / Down",
+        (true, SortBy::Download) => "Total Up / Down▼",
+        (true, SortBy::Total) => "Total Up + Down▼",
+        (true, _) => "Total Up / Down",
+    }
+}
+
+fn marked_column_name<'a>(name: &'a str, marked_name: &'a str, marked: bool) -> &'a str {
+    if marked {
+        marked_name
+    } else {
+        name
+    }
+}
+
+// the processes table lists processes by pid, grouped by name, or as a tree in which the
+// descendants of the collapsed pids are hidden
+#[derive(Clone, Copy)]
+pub enum ProcessView<'a> {
+    Pid,
+    Name,
+    Tree(&'a HashSet<u32>),
+}
+
+// a depth first walk of the tree, with the siblings sorted like the rows of the other views
+fn add_process_tree_rows<'a>(
+    tree: &'a ProcessTree,
+    parent: Option<u32>,
+    depth: usize,
+    collapsed: &HashSet<u32>,
+    sort_by: SortBy,
+    rows: &mut Vec<(String, &'a ProcessKey, &'a NetworkData)>,
+) {
+    let children = match tree.children.get(&parent) {
+        Some(children) => children,
+        None => return,
+    };
+    let siblings: BTreeMap<ProcessKey, NetworkData> = children
+        .iter()
+        .filter_map(|child| Some((child.clone(), tree.rolled_up.get(child)?.clone())))
+        .collect();
+    for (sibling, _) in sort_processes(&siblings, sort_by) {
+        let (process, data_for_process) = match tree.rolled_up.get_key_value(sibling) {
+            Some(entry) => entry,
+            None => continue,
         };
-        let b_highest = if b.get_total_bytes_downloaded() > b.get_total_bytes_uploaded() {
-            b.get_total_bytes_downloaded()
-        } else {
-            b.get_total_bytes_uploaded()
+        let has_children = process.pid.is_some() && tree.children.contains_key(&process.pid);
+        let expanded = has_children && !process.pid.map_or(false, |pid| collapsed.contains(&pid));
+        let marker = match (has_children, expanded) {
+            (false, _) => "  ",
+            (true, true) => "▾ ",
+            (true, false) => "▸ ",
         };
-        b_highest.cmp(&a_highest)
-    });
-    list
+        rows.push((
+            format!("{}{}{}", "  ".repeat(depth), marker, process.name),
+            process,
+            data_for_process,
+        ));
+        if expanded {
+            add_process_tree_rows(tree, process.pid, depth + 1, collapsed, sort_by, rows);
+        }
+    }
 }
 
 pub enum ColumnCount {
     Two,
     Three,
     Four,
+    Five,
+    Six,
 }
 
 impl ColumnCount {
@@ -51,6 +117,8 @@ impl ColumnCount {
             ColumnCount::Two => 2,
             ColumnCount::Three => 3,
             ColumnCount::Four => 4,
+            ColumnCount::Five => 5,
+            ColumnCount::Six => 6,
         }
     }
 }
@@ -62,15 +130,28 @@ pub struct ColumnData {
 
 pub struct Table<'a> {
     title: &'a str,
-    column_names: &'a [&'a str],
+    column_names: Vec<&'a str>,
     rows: Vec<Vec<String>>,
+    row_keys: Vec<RowKey>,
     breakpoints: BTreeMap<u16, ColumnData>,
+    focused: bool,
+    selected_row: Option<usize>,
+    scroll_offset: usize,
 }
 
+// counts characters rather than bytes, command lines are not necessarily ascii
 fn truncate_middle(row: &str, max_length: u16) -> String {
-    if row.len() as u16 > max_length {
-        let first_slice = &row[0..(max_length as usize / 2) - 2];
-        let second_slice = &row[(row.len() - (max_length / 2) as usize + 2)..row.len()];
+    let length = row.chars().count();
+    let max_length = max_length as usize;
+    if length > max_length {
+        let first_slice = row
+            .chars()
+            .take((max_length / 2).saturating_sub(2))
+            .collect::<String>();
+        let second_slice = row
+            .chars()
+            .skip(length - max_length / 2 + 2)
+            .collect::<String>();
         format!("{}[..]{}", first_slice, second_slice)
     } else {
         row.to_string()
@@ -78,25 +159,53 @@ fn truncate_middle(row: &str, max_length: u16) -> String {
 }
 
 impl<'a> Table<'a> {
-    pub fn create_connections_table(state: &UIState, ip_to_host: &HashMap<IpAddr, String>) -> Self {
-        let mut connections_list = Vec::from_iter(&state.connections);
-        sort_by_bandwidth(&mut connections_list);
+    pub fn create_connections_table(
+        state: &UIState,
+        ip_to_host: &HashMap<IpAddr, String>,
+        show_totals: bool,
+        sort_by: SortBy,
+    ) -> Self {
+        let connections = if show_totals {
+            &state.cumulative_connections
+        } else {
+            &state.connections
+        };
+        // the container column is only worth its room when some of the traffic is in containers
+        let show_containers = connections
+            .values()
+            .any(|connection_data| matches!(connection_data.container, ContainerKey::Container(_)));
+        let connections_list = sort_connections(connections, ip_to_host, sort_by);
         let connections_rows = connections_list
             .iter()
             .map(|(connection, connection_data)| {
-                vec![
-                    display_connection_string(
-                        &connection,
-                        &ip_to_host,
-                        &connection_data.interface_name,
-                    ),
-                    connection_data.process_name.to_string(),
-                    display_upload_and_download(*connection_data),
-                ]
+                let mut row = vec![display_connection_string(
+                    connection,
+                    ip_to_host,
+                    &connection_data.interface_name,
+                )];
+                if show_containers {
+                    row.push(connection_data.container.short_name());
+                }
+                row.push(connection_data.process.name.to_string());
+                row.push(display_upload_and_download(*connection_data, show_totals));
+                row
             })
             .collect();
+        let connections_keys = connections_list
+            .iter()
+            .map(|(connection, _)| RowKey::Connection(**connection))
+            .collect();
         let connections_title = "Utilization by connection";
-        let connections_column_names = &["Connection", "Process", "Rate Up / Down"];
+        let mut connections_column_names = vec![marked_column_name(
+            "Connection",
+            "Connection▲",
+            sort_by == SortBy::Name,
+        )];
+        if show_containers {
+            connections_column_names.push("Container");
+        }
+        connections_column_names.push("Process");
+        connections_column_names.push(bandwidth_column_name(show_totals, sort_by));
         let mut breakpoints = BTreeMap::new();
         breakpoints.insert(
             0,
@@ -112,42 +221,219 @@ impl<'a> Table<'a> {
                 column_widths: vec![30, 12, 23],
             },
         );
+        if show_containers {
+            breakpoints.insert(
+                100,
+                ColumnData {
+                    column_count: ColumnCount::Four,
+                    column_widths: vec![46, 14, 12, 23],
+                },
+            );
+            breakpoints.insert(
+                140,
+                ColumnData {
+                    column_count: ColumnCount::Four,
+                    column_widths: vec![86, 14, 12, 23],
+                },
+            );
+        } else {
+            breakpoints.insert(
+                100,
+                ColumnData {
+                    column_count: ColumnCount::Three,
+                    column_widths: vec![60, 12, 23],
+                },
+            );
+            breakpoints.insert(
+                140,
+                ColumnData {
+                    column_count: ColumnCount::Three,
+                    column_widths: vec![100, 12, 23],
+                },
+            );
+        }
+        Table {
+            title: connections_title,
+            column_names: connections_column_names,
+            rows: connections_rows,
+            row_keys: connections_keys,
+            breakpoints,
+            focused: false,
+            selected_row: None,
+            scroll_offset: 0,
+        }
+    }
+    pub fn create_processes_table(
+        state: &UIState,
+        show_totals: bool,
+        sort_by: SortBy,
+        view: ProcessView,
+    ) -> Self {
+        let processes = if show_totals {
+            &state.cumulative_processes
+        } else {
+            &state.processes
+        };
+        let group_by_name = matches!(view, ProcessView::Name);
+        let processes_by_name;
+        let process_tree;
+        let processes_list: Vec<(String, &ProcessKey, &NetworkData)> = match view {
+            ProcessView::Pid => sort_processes(processes, sort_by)
+                .into_iter()
+                .map(|(process, data)| (process.name.clone(), process, data))
+                .collect(),
+            ProcessView::Name => {
+                processes_by_name = group_processes_by_name(processes);
+                sort_processes(&processes_by_name, sort_by)
+                    .into_iter()
+                    .map(|(process, data)| (process.name.clone(), process, data))
+                    .collect()
+            }
+            ProcessView::Tree(collapsed) => {
+                process_tree = ProcessTree::new(processes, &state.process_info);
+                let mut rows = Vec::new();
+                add_process_tree_rows(&process_tree, None, 0, collapsed, sort_by, &mut rows);
+                rows
+            }
+        };
+        let processes_rows = processes_list
+            .iter()
+            .map(|(name, process, data_for_process)| {
+                let mut row = vec![name.clone()];
+                if !group_by_name {
+                    let process_info = state.get_process_info(process);
+                    row.push(
+                        process_info
+                            .map(|process_info| process_info.user.clone())
+                            .unwrap_or_default(),
+                    );
+                    row.push(process.pid.map(|pid| pid.to_string()).unwrap_or_default());
+                    row.push(
+                        process_info
+                            .map(|process_info| process_info.cmdline.clone())
+                            .unwrap_or_default(),
+                    );
+                }
+                row.push(data_for_process.connection_count.to_string());
+                row.push(display_upload_and_download(*data_for_process, show_totals));
+                row
+            })
+            .collect();
+        let processes_keys = processes_list
+            .iter()
+            .map(|(_, process, _)| RowKey::Process((*process).clone()))
+            .collect();
+        let processes_title = match view {
+            ProcessView::Pid => "Utilization by process",
+            ProcessView::Name => "Utilization by process name",
+            ProcessView::Tree(_) => "Utilization by process tree",
+        };
+        let mut processes_column_names = vec![marked_column_name(
+            "Process",
+            "Process▲",
+            sort_by == SortBy::Name,
+        )];
+        // the columns in between are lost from the left, so the command line goes last to be
+        // shown from four columns on
+        if !group_by_name {
+            processes_column_names.push("User");
+            processes_column_names.push("PID");
+            processes_column_names.push("Command line");
+        }
+        processes_column_names.push(marked_column_name(
+            "Connections",
+            "Connections▼",
+            sort_by == SortBy::Connections,
+        ));
+        processes_column_names.push(bandwidth_column_name(show_totals, sort_by));
+        let mut breakpoints = BTreeMap::new();
         breakpoints.insert(
-            100,
+            0,
             ColumnData {
-                column_count: ColumnCount::Three,
-                column_widths: vec![60, 12, 23],
+                column_count: ColumnCount::Two,
+                column_widths: vec![12, 23],
             },
         );
         breakpoints.insert(
-            140,
+            50,
             ColumnData {
                 column_count: ColumnCount::Three,
-                column_widths: vec![100, 12, 23],
+                column_widths: vec![12, 12, 23],
             },
         );
+        if group_by_name {
+            breakpoints.insert(
+                100,
+                ColumnData {
+                    column_count: ColumnCount::Three,
+                    column_widths: vec![40, 12, 23],
+                },
+            );
+        } else {
+            breakpoints.insert(
+                70,
+                ColumnData {
+                    column_count: ColumnCount::Four,
+                    column_widths: vec![16, 20, 12, 23],
+                },
+            );
+            breakpoints.insert(
+                100,
+                ColumnData {
+                    column_count: ColumnCount::Five,
+                    column_widths: vec![16, 8, 30, 12, 23],
+                },
+            );
+            breakpoints.insert(
+                140,
+                ColumnData {
+                    column_count: ColumnCount::Six,
+                    column_widths: vec![20, 12, 8, 40, 12, 23],
+                },
+            );
+        }
         Table {
-            title: connections_title,
-            column_names: connections_column_names,
-            rows: connections_rows,
+            title: processes_title,
+            column_names: processes_column_names,
+            rows: processes_rows,
+            row_keys: processes_keys,
             breakpoints,
+            focused: false,
+            selected_row: None,
+            scroll_offset: 0,
         }
     }
-    pub fn create_processes_table(state: &UIState) -> Self {
-        let mut processes_list = Vec::from_iter(&state.processes);
-        sort_by_bandwidth(&mut processes_list);
-        let processes_rows = processes_list
+    pub fn create_users_table(state: &UIState, show_totals: bool, sort_by: SortBy) -> Self {
+        let users = if show_totals {
+            &state.cumulative_users
+        } else {
+            &state.users
+        };
+        let users_list = sort_users(users, sort_by);
+        let users_rows = users_list
             .iter()
-            .map(|(process_name, data_for_process)| {
+            .map(|(user, data_for_user)| {
                 vec![
-                    (*process_name).to_string(),
-                    data_for_process.connection_count.to_string(),
-                    display_upload_and_download(*data_for_process),
+                    (*user).to_string(),
+                    data_for_user.connection_count.to_string(),
+                    display_upload_and_download(*data_for_user, show_totals),
                 ]
             })
             .collect();
-        let processes_title = "Utilization by process name";
-        let processes_column_names = &["Process", "Connections", "Rate Up / Down"];
+        let users_keys = users_list
+            .iter()
+            .map(|(user, _)| RowKey::User((*user).clone()))
+            .collect();
+        let users_title = "Utilization by user";
+        let users_column_names = vec![
+            marked_column_name("User", "User▲", sort_by == SortBy::Name),
+            marked_column_name(
+                "Connections",
+                "Connections▼",
+                sort_by == SortBy::Connections,
+            ),
+            bandwidth_column_name(show_totals, sort_by),
+        ];
         let mut breakpoints = BTreeMap::new();
         breakpoints.insert(
             0,
@@ -170,26 +456,269 @@ impl<'a> Table<'a> {
                 column_widths: vec![40, 12, 23],
             },
         );
+        Table {
+            title: users_title,
+            column_names: users_column_names,
+            rows: users_rows,
+            row_keys: users_keys,
+            breakpoints,
+            focused: false,
+            selected_row: None,
+            scroll_offset: 0,
+        }
+    }
+    pub fn create_containers_table(state: &UIState, show_totals: bool, sort_by: SortBy) -> Self {
+        let containers = if show_totals {
+            &state.cumulative_containers
+        } else {
+            &state.containers
+        };
+        let containers_list = sort_containers(containers, sort_by);
+        let containers_rows = containers_list
+            .iter()
+            .map(|(container, data_for_container)| {
+                let pod_uid = match container {
+                    ContainerKey::Container(container) => {
+                        container.pod_uid.clone().unwrap_or_default()
+                    }
+                    _ => String::new(),
+                };
+                vec![
+                    container.short_name(),
+                    pod_uid,
+                    data_for_container.connection_count.to_string(),
+                    display_upload_and_download(*data_for_container, show_totals),
+                ]
+            })
+            .collect();
+        let containers_keys = containers_list
+            .iter()
+            .map(|(container, _)| RowKey::Container((*container).clone()))
+            .collect();
+        let containers_title = "Utilization by container";
+        let containers_column_names = vec![
+            marked_column_name("Container", "Container▲", sort_by == SortBy::Name),
+            "Pod",
+            marked_column_name(
+                "Connections",
+                "Connections▼",
+                sort_by == SortBy::Connections,
+            ),
+            bandwidth_column_name(show_totals, sort_by),
+        ];
+        let mut breakpoints = BTreeMap::new();
         breakpoints.insert(
-            140,
+            0,
+            ColumnData {
+                column_count: ColumnCount::Two,
+                column_widths: vec![12, 23],
+            },
+        );
+        breakpoints.insert(
+            50,
             ColumnData {
                 column_count: ColumnCount::Three,
-                column_widths: vec![40, 12, 23],
+                column_widths: vec![12, 12, 23],
+            },
+        );
+        breakpoints.insert(
+            100,
+            ColumnData {
+                column_count: ColumnCount::Four,
+                column_widths: vec![14, 36, 12, 23],
             },
         );
         Table {
-            title: processes_title,
-            column_names: processes_column_names,
-            rows: processes_rows,
+            title: containers_title,
+            column_names: containers_column_names,
+            rows: containers_rows,
+            row_keys: containers_keys,
+            breakpoints,
+            focused: false,
+            selected_row: None,
+            scroll_offset: 0,
+        }
+    }
+    pub fn create_units_table(state: &UIState, show_totals: bool, sort_by: SortBy) -> Self {
+        let units = if show_totals {
+            &state.cumulative_units
+        } else {
+            &state.units
+        };
+        let units_list = sort_units(units, sort_by);
+        let units_rows = units_list
+            .iter()
+            .map(|(unit, data_for_unit)| {
+                vec![
+                    (*unit).to_string(),
+                    data_for_unit.connection_count.to_string(),
+                    display_upload_and_download(*data_for_unit, show_totals),
+                ]
+            })
+            .collect();
+        let units_keys = units_list
+            .iter()
+            .map(|(unit, _)| RowKey::Unit((*unit).clone()))
+            .collect();
+        let units_title = "Utilization by systemd unit";
+        let units_column_names = vec![
+            marked_column_name("Unit", "Unit▲", sort_by == SortBy::Name),
+            marked_column_name(
+                "Connections",
+                "Connections▼",
+                sort_by == SortBy::Connections,
+            ),
+            bandwidth_column_name(show_totals, sort_by),
+        ];
+        let mut breakpoints = BTreeMap::new();
+        breakpoints.insert(
+            0,
+            ColumnData {
+                column_count: ColumnCount::Two,
+                column_widths: vec![16, 23],
+            },
+        );
+        breakpoints.insert(
+            50,
+            ColumnData {
+                column_count: ColumnCount::Three,
+                column_widths: vec![16, 12, 23],
+            },
+        );
+        breakpoints.insert(
+            100,
+            ColumnData {
+                column_count: ColumnCount::Three,
+                column_widths: vec![50, 12, 23],
+            },
+        );
+        Table {
+            title: units_title,
+            column_names: units_column_names,
+            rows: units_rows,
+            row_keys: units_keys,
+            breakpoints,
+            focused: false,
+            selected_row: None,
+            scroll_offset: 0,
+        }
+    }
+    pub fn create_interfaces_table(state: &UIState, show_totals: bool, sort_by: SortBy) -> Self {
+        let interfaces = if show_totals {
+            &state.cumulative_interfaces
+        } else {
+            &state.interfaces
+        };
+        let interfaces_list = sort_interfaces(interfaces, sort_by);
+        let interfaces_rows = interfaces_list
+            .iter()
+            .map(|(interface_name, data_for_interface)| {
+                let counted = |value: String| {
+                    if data_for_interface.counted {
+                        value
+                    } else {
+                        String::from("-")
+                    }
+                };
+                // the share of the link is that of the current rate, even among the totals
+                let link_utilization = state
+                    .interfaces
+                    .get(*interface_name)
+                    .and_then(|data| data.link_utilization())
+                    .map(|percentage| format!("{:.0}%", percentage))
+                    .unwrap_or_else(|| String::from("-"));
+                vec![
+                    (*interface_name).to_string(),
+                    counted(format!(
+                        "{} / {}",
+                        data_for_interface.errors, data_for_interface.drops
+                    )),
+                    counted(data_for_interface.packets.to_string()),
+                    display_upload_and_download(&data_for_interface.sniffed, show_totals),
+                    link_utilization,
+                    counted(display_upload_and_download(
+                        *data_for_interface,
+                        show_totals,
+                    )),
+                ]
+            })
+            .collect();
+        let interfaces_keys = interfaces_list
+            .iter()
+            .map(|(interface_name, _)| RowKey::Interface((*interface_name).clone()))
+            .collect();
+        let interfaces_title = "Utilization by interface";
+        let interfaces_column_names = vec![
+            marked_column_name("Interface", "Interface▲", sort_by == SortBy::Name),
+            "Errors / Drops",
+            if show_totals { "Packets" } else { "Packets/s" },
+            if show_totals {
+                "Sniffed Up / Down"
+            } else {
+                "Sniffed Rate Up / Down"
+            },
+            "Link",
+            bandwidth_column_name(show_totals, sort_by),
+        ];
+        let mut breakpoints = BTreeMap::new();
+        breakpoints.insert(
+            0,
+            ColumnData {
+                column_count: ColumnCount::Two,
+                column_widths: vec![12, 23],
+            },
+        );
+        breakpoints.insert(
+            50,
+            ColumnData {
+                column_count: ColumnCount::Three,
+                column_widths: vec![12, 5, 23],
+            },
+        );
+        breakpoints.insert(
+            80,
+            ColumnData {
+                column_count: ColumnCount::Four,
+                column_widths: vec![12, 23, 5, 23],
+            },
+        );
+        breakpoints.insert(
+            120,
+            ColumnData {
+                column_count: ColumnCount::Five,
+                column_widths: vec![12, 10, 23, 5, 23],
+            },
+        );
+        breakpoints.insert(
+            150,
+            ColumnData {
+                column_count: ColumnCount::Six,
+                column_widths: vec![16, 15, 10, 23, 5, 23],
+            },
+        );
+        Table {
+            title: interfaces_title,
+            column_names: interfaces_column_names,
+            rows: interfaces_rows,
+            row_keys: interfaces_keys,
             breakpoints,
+            focused: false,
+            selected_row: None,
+            scroll_offset: 0,
         }
     }
     pub fn create_remote_addresses_table(
         state: &UIState,
         ip_to_host: &HashMap<IpAddr, String>,
+        show_totals: bool,
+        sort_by: SortBy,
     ) -> Self {
-        let mut remote_addresses_list = Vec::from_iter(&state.remote_addresses);
-        sort_by_bandwidth(&mut remote_addresses_list);
+        let remote_addresses = if show_totals {
+            &state.cumulative_remote_addresses
+        } else {
+            &state.remote_addresses
+        };
+        let remote_addresses_list = sort_remote_addresses(remote_addresses, ip_to_host, sort_by);
         let remote_addresses_rows = remote_addresses_list
             .iter()
             .map(|(remote_address, data_for_remote_address)| {
@@ -197,12 +726,24 @@ impl<'a> Table<'a> {
                 vec![
                     remote_address,
                     data_for_remote_address.connection_count.to_string(),
-                    display_upload_and_download(*data_for_remote_address),
+                    display_upload_and_download(*data_for_remote_address, show_totals),
                 ]
             })
             .collect();
+        let remote_addresses_keys = remote_addresses_list
+            .iter()
+            .map(|(remote_address, _)| RowKey::RemoteAddress(**remote_address))
+            .collect();
         let remote_addresses_title = "Utilization by remote address";
-        let remote_addresses_column_names = &["Remote Address", "Connections", "Rate Up / Down"];
+        let remote_addresses_column_names = vec![
+            marked_column_name("Remote Address", "Remote Address▲", sort_by == SortBy::Name),
+            marked_column_name(
+                "Connections",
+                "Connections▼",
+                sort_by == SortBy::Connections,
+            ),
+            bandwidth_column_name(show_totals, sort_by),
+        ];
         let mut breakpoints = BTreeMap::new();
         breakpoints.insert(
             0,
@@ -236,10 +777,23 @@ impl<'a> Table<'a> {
             title: remote_addresses_title,
             column_names: remote_addresses_column_names,
             rows: remote_addresses_rows,
+            row_keys: remote_addresses_keys,
             breakpoints,
+            focused: false,
+            selected_row: None,
+            scroll_offset: 0,
         }
     }
-    pub fn render(&self, frame: &mut Frame<impl Backend>, rect: Rect) {
+    pub fn row_keys(&self) -> &[RowKey] {
+        &self.row_keys
+    }
+    pub fn focus(&mut self, selected_row: usize, scroll_offset: usize) {
+        self.focused = true;
+        self.selected_row = Some(selected_row);
+        self.scroll_offset = scroll_offset;
+    }
+    // returns the number of rows that fit in the table, so the caller knows how far a page goes
+    pub fn render(&self, frame: &mut Frame<impl Backend>, rect: Rect) -> usize {
         let mut column_spacing: u16 = 0;
         let mut widths = &vec![];
         let mut column_count: &ColumnCount = &ColumnCount::Three;
@@ -258,49 +812,76 @@ impl<'a> Table<'a> {
             }
         }
 
-        let column_names = match column_count {
-            ColumnCount::Two => {
-                vec![self.column_names[0], self.column_names[2]] // always lose the middle column when needed
-            }
-            ColumnCount::Three => vec![
-                self.column_names[0],
-                self.column_names[1],
-                self.column_names[2],
-            ],
-            ColumnCount::Four => vec![
-                self.column_names[0],
-                self.column_names[1],
-                self.column_names[2],
-                self.column_names[3],
-            ],
-        };
+        // the first and the last columns are always shown, the ones in between are lost from the
+        // left when needed
+        let first_column_shown = self.column_names.len() + 1 - column_count.as_u16() as usize;
+        let columns = iter::once(0)
+            .chain(first_column_shown..self.column_names.len())
+            .collect::<Vec<_>>();
+        let column_names = columns
+            .iter()
+            .map(|column| self.column_names[*column])
+            .collect::<Vec<_>>();
+
+        // the borders take two lines, the header and the gap below it another two
+        let page_size = rect.height.saturating_sub(4) as usize;
+        let mut scroll_offset = self
+            .scroll_offset
+            .min(self.rows.len().saturating_sub(page_size));
+        if let Some(selected_row) = self.selected_row {
+            // the selected row is always kept in view
+            scroll_offset = scroll_offset
+                .min(selected_row)
+                .max((selected_row + 1).saturating_sub(page_size));
+        }
 
-        let rows = self.rows.iter().map(|row| match column_count {
-            ColumnCount::Two => vec![
-                truncate_middle(&row[0], widths[0]),
-                truncate_middle(&row[2], widths[1]),
-            ],
-            ColumnCount::Three => vec![
-                truncate_middle(&row[0], widths[0]),
-                truncate_middle(&row[1], widths[1]),
-                truncate_middle(&row[2], widths[2]),
-            ],
-            ColumnCount::Four => vec![
-                truncate_middle(&row[0], widths[0]),
-                truncate_middle(&row[1], widths[1]),
-                truncate_middle(&row[2], widths[2]),
-                truncate_middle(&row[3], widths[3]),
-            ],
+        let rows = self.rows.iter().map(|row| {
+            columns
+                .iter()
+                .zip(widths.iter())
+                .map(|(column, width)| truncate_middle(&row[*column], *width))
+                .collect::<Vec<_>>()
         });
 
-        let table_rows = rows.map(|row| Row::StyledData(row.into_iter(), Style::default()));
+        let selected_row = self.selected_row;
+        let table_rows = rows.enumerate().skip(scroll_offset).map(|(index, row)| {
+            let style = if Some(index) == selected_row {
+                Style::default().modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            Row::StyledData(row.into_iter(), style)
+        });
+
+        let title = if self.rows.len() > page_size && page_size > 0 {
+            format!(
+                "{} ({}-{} of {})",
+                self.title,
+                scroll_offset + 1,
+                (scroll_offset + page_size).min(self.rows.len()),
+                self.rows.len()
+            )
+        } else {
+            self.title.to_string()
+        };
+        let border_style = if self.focused {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
 
         ::tui::widgets::Table::new(column_names.into_iter(), table_rows)
-            .block(Block::default().title(self.title).borders(Borders::ALL))
+            .block(
+                Block::default()
+                    .title(&title)
+                    .borders(Borders::ALL)
+                    .border_style(border_style),
+            )
             .header_style(Style::default().fg(Color::Yellow))
             .widths(&widths[..])
             .style(Style::default())
             .column_spacing(column_spacing)
             .render(frame, rect);
+        page_size
     }
 }
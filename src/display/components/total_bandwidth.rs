@@ -4,32 +4,70 @@ use ::tui::style::{Color, Modifier, Style};
 use ::tui::terminal::Frame;
 use ::tui::widgets::{Paragraph, Text, Widget};
 
-use crate::display::{DisplayBandwidth, UIState};
+use crate::display::{DisplayBandwidth, DisplayBytes, UIState};
 
 pub struct TotalBandwidth<'a> {
     pub state: &'a UIState,
     pub paused: bool,
+    pub show_totals: bool,
+    pub filtered: bool,
+    pub counters_only: bool,
 }
 
 impl<'a> TotalBandwidth<'a> {
     pub fn render(&self, frame: &mut Frame<impl Backend>, rect: Rect) {
         let title_text = {
             let paused_str = if self.paused { "[PAUSED]" } else { "" };
+            let filtered_str = if self.filtered { "[FILTERED]" } else { "" };
+            let counters_only_str = if self.counters_only {
+                "[TCP COUNTERS ONLY]"
+            } else {
+                ""
+            };
             let color = if self.paused {
                 Color::Yellow
             } else {
                 Color::Green
             };
 
-            [Text::styled(
+            let totals = if self.show_totals {
                 format!(
-                    " Total Rate Up / Down: {} / {} {}",
+                    " Total Up / Down: {} / {}",
+                    DisplayBytes(self.state.cumulative_bytes_uploaded as f64),
+                    DisplayBytes(self.state.cumulative_bytes_downloaded as f64),
+                )
+            } else {
+                format!(
+                    " Total Rate Up / Down: {} / {}",
                     DisplayBandwidth(self.state.total_bytes_uploaded as f64),
                     DisplayBandwidth(self.state.total_bytes_downloaded as f64),
-                    paused_str
+                )
+            };
+
+            // the traffic of dropped packets is missing from the rates, which warrants attention
+            // for as long as they are counted over
+            let capture_drops = self.state.recent_capture_drops();
+            let drops_str = if capture_drops > 0 {
+                format!(" [DROPPED {} PACKETS]", capture_drops)
+            } else {
+                String::new()
+            };
+
+            [
+                Text::styled(
+                    format!(
+                        "{} {}{}{}",
+                        totals, counters_only_str, filtered_str, paused_str
+                    )
+                    .trim_end()
+                    .to_string(),
+                    Style::default().fg(color).modifier(Modifier::BOLD),
+                ),
+                Text::styled(
+                    drops_str,
+                    Style::default().fg(Color::Red).modifier(Modifier::BOLD),
                 ),
-                Style::default().fg(color).modifier(Modifier::BOLD),
-            )]
+            ]
         };
         Paragraph::new(title_text.iter())
             .alignment(Alignment::Left)
@@ -0,0 +1,137 @@
+use ::std::collections::HashMap;
+use ::std::net::IpAddr;
+
+use crate::display::{
+    sort_connections, sort_processes, sort_remote_addresses, sort_users, SortBy, UIState,
+};
+
+pub const CSV_HEADER: &str = "timestamp,type,process,interface,protocol,local_ip,local_port,remote_ip,remote_port,remote_host,upload_bytes_per_second,download_bytes_per_second,connections,pid,cmdline,exe,user";
+
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn csv_line(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+pub fn csv_rows(
+    state: &UIState,
+    ip_to_host: &HashMap<IpAddr, String>,
+    sort_by: SortBy,
+    timestamp: i64,
+) -> Vec<String> {
+    let empty = String::new;
+    let host = |ip: &IpAddr| ip_to_host.get(ip).cloned().unwrap_or_default();
+    let mut rows = Vec::new();
+    for (process, process_network_data) in sort_processes(&state.processes, sort_by) {
+        let process_info = state.get_process_info(process);
+        rows.push(csv_line(&[
+            timestamp.to_string(),
+            "process".to_string(),
+            process.name.clone(),
+            empty(),
+            empty(),
+            empty(),
+            empty(),
+            empty(),
+            empty(),
+            empty(),
+            process_network_data.total_bytes_uploaded.to_string(),
+            process_network_data.total_bytes_downloaded.to_string(),
+            process_network_data.connection_count.to_string(),
+            process.pid.map(|pid| pid.to_string()).unwrap_or_default(),
+            process_info
+                .map(|process_info| process_info.cmdline.clone())
+                .unwrap_or_default(),
+            process_info
+                .map(|process_info| process_info.exe.clone())
+                .unwrap_or_default(),
+            process_info
+                .map(|process_info| process_info.user.clone())
+                .unwrap_or_default(),
+        ]));
+    }
+    for (connection, connection_network_data) in
+        sort_connections(&state.connections, ip_to_host, sort_by)
+    {
+        rows.push(csv_line(&[
+            timestamp.to_string(),
+            "connection".to_string(),
+            connection_network_data.process.name.clone(),
+            connection_network_data.interface_name.clone(),
+            connection.local_socket.protocol.to_string(),
+            connection.local_socket.ip.to_string(),
+            connection.local_socket.port.to_string(),
+            connection.remote_socket.ip.to_string(),
+            connection.remote_socket.port.to_string(),
+            host(&connection.remote_socket.ip),
+            connection_network_data.total_bytes_uploaded.to_string(),
+            connection_network_data.total_bytes_downloaded.to_string(),
+            empty(),
+            connection_network_data
+                .process
+                .pid
+                .map(|pid| pid.to_string())
+                .unwrap_or_default(),
+            empty(),
+            empty(),
+            empty(),
+        ]));
+    }
+    for (remote_address, remote_address_network_data) in
+        sort_remote_addresses(&state.remote_addresses, ip_to_host, sort_by)
+    {
+        rows.push(csv_line(&[
+            timestamp.to_string(),
+            "remote_address".to_string(),
+            empty(),
+            empty(),
+            empty(),
+            empty(),
+            empty(),
+            remote_address.to_string(),
+            empty(),
+            host(remote_address),
+            remote_address_network_data.total_bytes_uploaded.to_string(),
+            remote_address_network_data
+                .total_bytes_downloaded
+                .to_string(),
+            remote_address_network_data.connection_count.to_string(),
+            empty(),
+            empty(),
+            empty(),
+            empty(),
+        ]));
+    }
+    for (user, user_network_data) in sort_users(&state.users, sort_by) {
+        rows.push(csv_line(&[
+            timestamp.to_string(),
+            "user".to_string(),
+            empty(),
+            empty(),
+            empty(),
+            empty(),
+            empty(),
+            empty(),
+            empty(),
+            empty(),
+            user_network_data.total_bytes_uploaded.to_string(),
+            user_network_data.total_bytes_downloaded.to_string(),
+            user_network_data.connection_count.to_string(),
+            empty(),
+            empty(),
+            empty(),
+            user.clone(),
+        ]));
+    }
+    rows
+}
@@ -0,0 +1,63 @@
+use ::std::collections::HashMap;
+use ::std::net::IpAddr;
+
+use ::regex::{Regex, RegexBuilder};
+
+use crate::display::{ConnectionData, UIState};
+use crate::network::{display_connection_string, Connection};
+
+pub struct Filter {
+    pub pattern: String,
+    regex: Regex,
+}
+
+impl Filter {
+    // the pattern is a case insensitive regex, or a plain substring if it does not compile as one
+    pub fn new(pattern: &str) -> Self {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .unwrap_or_else(|_| {
+                RegexBuilder::new(&regex::escape(pattern))
+                    .case_insensitive(true)
+                    .build()
+                    .unwrap()
+            });
+        Filter {
+            pattern: pattern.to_string(),
+            regex,
+        }
+    }
+    fn matches_connection(
+        &self,
+        connection: &Connection,
+        connection_data: &ConnectionData,
+        ip_to_host: &HashMap<IpAddr, String>,
+    ) -> bool {
+        let local_socket = &connection.local_socket;
+        let remote_socket = &connection.remote_socket;
+        [
+            display_connection_string(connection, ip_to_host, &connection_data.interface_name),
+            connection_data.process.name.clone(),
+            format!("{}:{}", local_socket.ip, local_socket.port),
+            format!("{}:{}", remote_socket.ip, remote_socket.port),
+        ]
+        .iter()
+        .any(|field| self.regex.is_match(field))
+    }
+    // the tables are built from the matching connections alone, so a process or a remote address
+    // is listed with the traffic of its matching connections
+    pub fn apply(
+        &self,
+        state: &UIState,
+        ip_to_host: &HashMap<IpAddr, String>,
+        show_totals: bool,
+    ) -> UIState {
+        state.filtered(
+            |connection, connection_data| {
+                self.matches_connection(connection, connection_data, ip_to_host)
+            },
+            show_totals,
+        )
+    }
+}
@@ -0,0 +1,145 @@
+use ::std::collections::HashMap;
+use ::std::net::IpAddr;
+
+use ::serde::Serialize;
+
+use crate::display::{
+    sort_connections, sort_processes, sort_remote_addresses, sort_users, ProcessKey, SortBy,
+    UIState,
+};
+use crate::network::Protocol;
+use crate::ProcessInfo;
+
+// bump this whenever a field is renamed, removed or changes its meaning (see docs/json_output.md)
+pub const JSON_SCHEMA_VERSION: u32 = 2;
+
+#[derive(Serialize)]
+pub struct JsonLine<'a> {
+    pub version: u32,
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub row: JsonRow<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JsonRow<'a> {
+    Totals {
+        upload_bytes_per_second: u128,
+        download_bytes_per_second: u128,
+        dropped_packets: u64,
+    },
+    Process {
+        name: &'a str,
+        pid: Option<u32>,
+        cmdline: Option<&'a str>,
+        exe: Option<&'a str>,
+        user: Option<&'a str>,
+        upload_bytes_per_second: u128,
+        download_bytes_per_second: u128,
+        connections: u128,
+    },
+    Connection {
+        interface: &'a str,
+        protocol: Protocol,
+        local_ip: IpAddr,
+        local_port: u16,
+        remote_ip: IpAddr,
+        remote_port: u16,
+        remote_host: Option<&'a str>,
+        process: Option<&'a str>,
+        pid: Option<u32>,
+        upload_bytes_per_second: u128,
+        download_bytes_per_second: u128,
+    },
+    RemoteAddress {
+        ip: IpAddr,
+        host: Option<&'a str>,
+        upload_bytes_per_second: u128,
+        download_bytes_per_second: u128,
+        connections: u128,
+    },
+    User {
+        name: &'a str,
+        upload_bytes_per_second: u128,
+        download_bytes_per_second: u128,
+        connections: u128,
+    },
+}
+
+pub fn json_rows<'a>(
+    state: &'a UIState,
+    ip_to_host: &'a HashMap<IpAddr, String>,
+    sort_by: SortBy,
+) -> Vec<JsonRow<'a>> {
+    let mut rows = vec![JsonRow::Totals {
+        upload_bytes_per_second: state.total_bytes_uploaded,
+        download_bytes_per_second: state.total_bytes_downloaded,
+        dropped_packets: state.new_capture_drops.values().sum(),
+    }];
+    for (process, process_network_data) in sort_processes(&state.processes, sort_by) {
+        let process_info = state.get_process_info(process);
+        let describe = |attribute: fn(&'a ProcessInfo) -> &'a String| {
+            process_info
+                .map(attribute)
+                .filter(|value| !value.is_empty())
+                .map(String::as_str)
+        };
+        rows.push(JsonRow::Process {
+            name: &process.name,
+            pid: process.pid,
+            cmdline: describe(|process_info| &process_info.cmdline),
+            exe: describe(|process_info| &process_info.exe),
+            user: describe(|process_info| &process_info.user),
+            upload_bytes_per_second: process_network_data.total_bytes_uploaded,
+            download_bytes_per_second: process_network_data.total_bytes_downloaded,
+            connections: process_network_data.connection_count,
+        });
+    }
+    for (connection, connection_network_data) in
+        sort_connections(&state.connections, ip_to_host, sort_by)
+    {
+        let process = &connection_network_data.process;
+        rows.push(JsonRow::Connection {
+            interface: &connection_network_data.interface_name,
+            protocol: connection.local_socket.protocol,
+            local_ip: connection.local_socket.ip,
+            local_port: connection.local_socket.port,
+            remote_ip: connection.remote_socket.ip,
+            remote_port: connection.remote_socket.port,
+            remote_host: ip_to_host
+                .get(&connection.remote_socket.ip)
+                .map(String::as_str),
+            // the process is not known when it could not be found, or cannot be looked up at all
+            // when reading a capture
+            process: if process.name.is_empty() || *process == ProcessKey::unknown() {
+                None
+            } else {
+                Some(&process.name)
+            },
+            pid: process.pid,
+            upload_bytes_per_second: connection_network_data.total_bytes_uploaded,
+            download_bytes_per_second: connection_network_data.total_bytes_downloaded,
+        });
+    }
+    for (remote_address, remote_address_network_data) in
+        sort_remote_addresses(&state.remote_addresses, ip_to_host, sort_by)
+    {
+        rows.push(JsonRow::RemoteAddress {
+            ip: *remote_address,
+            host: ip_to_host.get(remote_address).map(String::as_str),
+            upload_bytes_per_second: remote_address_network_data.total_bytes_uploaded,
+            download_bytes_per_second: remote_address_network_data.total_bytes_downloaded,
+            connections: remote_address_network_data.connection_count,
+        });
+    }
+    for (user, user_network_data) in sort_users(&state.users, sort_by) {
+        rows.push(JsonRow::User {
+            name: user,
+            upload_bytes_per_second: user_network_data.total_bytes_uploaded,
+            download_bytes_per_second: user_network_data.total_bytes_downloaded,
+            connections: user_network_data.connection_count,
+        });
+    }
+    rows
+}
@@ -1,9 +1,19 @@
 mod components;
+mod csv_output;
+mod filter;
+mod json_output;
+mod prometheus;
 mod raw_terminal_backend;
+mod sort;
 mod ui;
 mod ui_state;
 
 pub use components::*;
+pub use csv_output::*;
+pub use filter::*;
+pub use json_output::*;
+pub use prometheus::*;
 pub use raw_terminal_backend::*;
+pub use sort::*;
 pub use ui::*;
 pub use ui_state::*;
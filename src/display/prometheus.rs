@@ -0,0 +1,253 @@
+use ::std::collections::{BTreeMap, HashMap};
+use ::std::io::{self, BufRead, BufReader, Write};
+use ::std::net::{IpAddr, TcpListener, TcpStream};
+use ::std::sync::{Arc, Mutex};
+use ::std::time::Duration;
+
+use crate::network::{get_socket_owner, Connection, ConnectionInfo, LocalSocket, Utilization};
+use crate::ProcessInfo;
+
+// connections and remote addresses come and go, so only this many of each get a series of their own,
+// those that went without traffic the longest give theirs up to the new ones
+pub const MAX_CONNECTION_SERIES: usize = 1000;
+pub const MAX_REMOTE_ADDRESS_SERIES: usize = 1000;
+
+#[derive(Default)]
+struct ByteCounters {
+    uploaded: u128,
+    downloaded: u128,
+    // the update the counters last grew in
+    last_updated: u64,
+}
+
+impl ByteCounters {
+    fn add(&mut self, connection_info: &ConnectionInfo) {
+        self.uploaded += connection_info.total_bytes_uploaded;
+        self.downloaded += connection_info.total_bytes_downloaded;
+    }
+}
+
+#[derive(Default)]
+pub struct PrometheusMetrics {
+    // there is nothing to attribute the traffic to when it is read from a capture
+    process_info_available: bool,
+    ticks: u64,
+    // by name rather than by pid, which would start a new series every time a process restarts
+    processes: BTreeMap<String, ByteCounters>,
+    remote_addresses: BTreeMap<IpAddr, ByteCounters>,
+    interfaces: BTreeMap<String, ByteCounters>,
+    connections: BTreeMap<(String, Connection), ByteCounters>,
+    untracked_remote_addresses: ByteCounters,
+    untracked_connections: ByteCounters,
+}
+
+// the traffic of a key is added to its series. Once there are too many, the series that was idle the
+// longest is folded into the untracked one to make room, so that the sum of all of them still only
+// grows. The traffic goes to the untracked series when they all had some in this update
+fn add_to_series<K: Ord + Clone>(
+    series: &mut BTreeMap<K, ByteCounters>,
+    untracked: &mut ByteCounters,
+    max_series: usize,
+    tick: u64,
+    key: K,
+    connection_info: &ConnectionInfo,
+) {
+    if !series.contains_key(&key) && series.len() >= max_series {
+        let idlest = series
+            .iter()
+            .min_by_key(|(_, counters)| counters.last_updated)
+            .filter(|(_, counters)| counters.last_updated < tick)
+            .map(|(key, _)| key.clone());
+        match idlest.and_then(|idlest| series.remove(&idlest)) {
+            Some(evicted) => {
+                untracked.uploaded += evicted.uploaded;
+                untracked.downloaded += evicted.downloaded;
+            }
+            None => {
+                untracked.add(connection_info);
+                return;
+            }
+        }
+    }
+    let counters = series.entry(key).or_default();
+    counters.add(connection_info);
+    counters.last_updated = tick;
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_counter<'a>(
+    output: &mut String,
+    name: &str,
+    help: &str,
+    series: impl Iterator<Item = (String, &'a ByteCounters)>,
+) {
+    output.push_str(&format!("# HELP {} {}\n", name, help));
+    output.push_str(&format!("# TYPE {} counter\n", name));
+    for (labels, counters) in series {
+        let separator = if labels.is_empty() { "" } else { "," };
+        output.push_str(&format!(
+            "{}{{{}{}direction=\"upload\"}} {}\n",
+            name, labels, separator, counters.uploaded
+        ));
+        output.push_str(&format!(
+            "{}{{{}{}direction=\"download\"}} {}\n",
+            name, labels, separator, counters.downloaded
+        ));
+    }
+}
+
+impl PrometheusMetrics {
+    pub fn new(process_info_available: bool) -> Self {
+        PrometheusMetrics {
+            process_info_available,
+            ..Default::default()
+        }
+    }
+    pub fn update(
+        &mut self,
+        connections_to_procs: &HashMap<LocalSocket, ProcessInfo>,
+        network_utilization: &Utilization,
+    ) {
+        self.ticks += 1;
+        for (connection, connection_info) in &network_utilization.connections {
+            add_to_series(
+                &mut self.remote_addresses,
+                &mut self.untracked_remote_addresses,
+                MAX_REMOTE_ADDRESS_SERIES,
+                self.ticks,
+                connection.remote_socket.ip,
+                connection_info,
+            );
+            self.interfaces
+                .entry(connection_info.interface_name.clone())
+                .or_default()
+                .add(connection_info);
+            add_to_series(
+                &mut self.connections,
+                &mut self.untracked_connections,
+                MAX_CONNECTION_SERIES,
+                self.ticks,
+                (connection_info.interface_name.clone(), *connection),
+                connection_info,
+            );
+            if !self.process_info_available {
+                continue;
+            }
+            let process_name = get_socket_owner(connections_to_procs, &connection.local_socket)
+                .map(|process_info| process_info.name.as_str())
+                .unwrap_or("<UNKNOWN>");
+            self.processes
+                .entry(process_name.to_string())
+                .or_default()
+                .add(connection_info);
+        }
+    }
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        write_counter(
+            &mut output,
+            "bandwhich_process_bytes_total",
+            "Bytes transferred by a process.",
+            self.processes.iter().map(|(process_name, counters)| {
+                (
+                    format!("process=\"{}\"", escape_label_value(process_name)),
+                    counters,
+                )
+            }),
+        );
+        write_counter(
+            &mut output,
+            "bandwhich_remote_address_bytes_total",
+            "Bytes transferred to and from a remote address.",
+            self.remote_addresses
+                .iter()
+                .map(|(ip, counters)| (format!("remote_ip=\"{}\"", ip), counters)),
+        );
+        write_counter(
+            &mut output,
+            "bandwhich_interface_bytes_total",
+            "Bytes transferred on a network interface.",
+            self.interfaces.iter().map(|(interface_name, counters)| {
+                (
+                    format!("interface=\"{}\"", escape_label_value(interface_name)),
+                    counters,
+                )
+            }),
+        );
+        write_counter(
+            &mut output,
+            "bandwhich_connection_bytes_total",
+            "Bytes transferred on a connection.",
+            self.connections
+                .iter()
+                .map(|((interface_name, connection), counters)| {
+                    (
+                        format!(
+                            "interface=\"{}\",protocol=\"{}\",local_ip=\"{}\",local_port=\"{}\",remote_ip=\"{}\",remote_port=\"{}\"",
+                            escape_label_value(interface_name),
+                            connection.local_socket.protocol,
+                            connection.local_socket.ip,
+                            connection.local_socket.port,
+                            connection.remote_socket.ip,
+                            connection.remote_socket.port,
+                        ),
+                        counters,
+                    )
+                }),
+        );
+        write_counter(
+            &mut output,
+            "bandwhich_untracked_remote_address_bytes_total",
+            "Bytes transferred to and from remote addresses without a series of their own.",
+            Some((String::new(), &self.untracked_remote_addresses)).into_iter(),
+        );
+        write_counter(
+            &mut output,
+            "bandwhich_untracked_connection_bytes_total",
+            "Bytes transferred on connections without a series of their own.",
+            Some((String::new(), &self.untracked_connections)).into_iter(),
+        );
+        output
+    }
+}
+
+fn respond(mut stream: TcpStream, metrics: &Mutex<PrometheusMetrics>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // the headers are read (and ignored) so the client is not reset when the stream is closed
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|target| target.split('?').next());
+    let (status, body) = match path {
+        Some("/metrics") => ("200 OK", metrics.lock().unwrap().render()),
+        _ => ("404 Not Found", String::from("Not Found\n")),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+pub fn serve_prometheus_metrics(listener: TcpListener, metrics: Arc<Mutex<PrometheusMetrics>>) {
+    for stream in listener.incoming().flatten() {
+        // a misbehaving scraper should not bring the exporter down
+        let _ = respond(stream, &metrics);
+    }
+}
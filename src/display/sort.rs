@@ -0,0 +1,165 @@
+use ::std::cmp::Ordering;
+use ::std::collections::{BTreeMap, HashMap};
+use ::std::iter::FromIterator;
+use ::std::net::IpAddr;
+use ::std::str::FromStr;
+
+use crate::display::{
+    Bandwidth, ConnectionData, ContainerKey, InterfaceData, NetworkData, ProcessKey,
+};
+use crate::network::{display_connection_string, display_ip_or_host, Connection};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SortBy {
+    Bandwidth,
+    Upload,
+    Download,
+    Total,
+    Connections,
+    Name,
+}
+
+impl SortBy {
+    pub fn next(self) -> Self {
+        match self {
+            SortBy::Bandwidth => SortBy::Upload,
+            SortBy::Upload => SortBy::Download,
+            SortBy::Download => SortBy::Total,
+            SortBy::Total => SortBy::Connections,
+            SortBy::Connections => SortBy::Name,
+            SortBy::Name => SortBy::Bandwidth,
+        }
+    }
+}
+
+impl FromStr for SortBy {
+    type Err = &'static str;
+    fn from_str(sort_by: &str) -> Result<Self, Self::Err> {
+        match sort_by {
+            "bandwidth" => Ok(SortBy::Bandwidth),
+            "upload" => Ok(SortBy::Upload),
+            "download" => Ok(SortBy::Download),
+            "total" => Ok(SortBy::Total),
+            "connections" => Ok(SortBy::Connections),
+            "name" => Ok(SortBy::Name),
+            _ => Err("Unknown sort order"),
+        }
+    }
+}
+
+fn highest(bandwidth: &impl Bandwidth) -> u128 {
+    if bandwidth.get_total_bytes_downloaded() > bandwidth.get_total_bytes_uploaded() {
+        bandwidth.get_total_bytes_downloaded()
+    } else {
+        bandwidth.get_total_bytes_uploaded()
+    }
+}
+
+fn compare(a: &impl Bandwidth, b: &impl Bandwidth, sort_by: SortBy) -> Ordering {
+    match sort_by {
+        SortBy::Bandwidth => highest(b).cmp(&highest(a)),
+        SortBy::Upload => b
+            .get_total_bytes_uploaded()
+            .cmp(&a.get_total_bytes_uploaded()),
+        SortBy::Download => b
+            .get_total_bytes_downloaded()
+            .cmp(&a.get_total_bytes_downloaded()),
+        SortBy::Total => (b.get_total_bytes_uploaded() + b.get_total_bytes_downloaded())
+            .cmp(&(a.get_total_bytes_uploaded() + a.get_total_bytes_downloaded())),
+        SortBy::Connections => b.get_connection_count().cmp(&a.get_connection_count()),
+        SortBy::Name => Ordering::Equal,
+    }
+}
+
+// names are compared as they are displayed, so a resolved host sorts by its host name
+fn sort_list<T, B: Bandwidth>(
+    list: &mut [(T, &B)],
+    sort_by: SortBy,
+    display_name: impl Fn(&T, &B) -> String,
+) {
+    if sort_by == SortBy::Name {
+        list.sort_by_cached_key(|(key, data)| display_name(key, data));
+    } else {
+        list.sort_by(|(_, a), (_, b)| compare(*a, *b, sort_by));
+    }
+}
+
+// processes of the same name stay in the order of their pids
+pub fn sort_processes(
+    processes: &BTreeMap<ProcessKey, NetworkData>,
+    sort_by: SortBy,
+) -> Vec<(&ProcessKey, &NetworkData)> {
+    let mut processes_list = Vec::from_iter(processes);
+    sort_list(&mut processes_list, sort_by, |process, _| {
+        process.name.to_string()
+    });
+    processes_list
+}
+
+pub fn sort_users(
+    users: &BTreeMap<String, NetworkData>,
+    sort_by: SortBy,
+) -> Vec<(&String, &NetworkData)> {
+    let mut users_list = Vec::from_iter(users);
+    sort_list(&mut users_list, sort_by, |user, _| user.to_string());
+    users_list
+}
+
+pub fn sort_containers(
+    containers: &BTreeMap<ContainerKey, NetworkData>,
+    sort_by: SortBy,
+) -> Vec<(&ContainerKey, &NetworkData)> {
+    let mut containers_list = Vec::from_iter(containers);
+    sort_list(&mut containers_list, sort_by, |container, _| {
+        container.short_name()
+    });
+    containers_list
+}
+
+pub fn sort_units(
+    units: &BTreeMap<String, NetworkData>,
+    sort_by: SortBy,
+) -> Vec<(&String, &NetworkData)> {
+    let mut units_list = Vec::from_iter(units);
+    sort_list(&mut units_list, sort_by, |unit, _| unit.to_string());
+    units_list
+}
+
+pub fn sort_interfaces(
+    interfaces: &BTreeMap<String, InterfaceData>,
+    sort_by: SortBy,
+) -> Vec<(&String, &InterfaceData)> {
+    let mut interfaces_list = Vec::from_iter(interfaces);
+    sort_list(&mut interfaces_list, sort_by, |interface_name, _| {
+        interface_name.to_string()
+    });
+    interfaces_list
+}
+
+pub fn sort_remote_addresses<'a>(
+    remote_addresses: &'a BTreeMap<IpAddr, NetworkData>,
+    ip_to_host: &HashMap<IpAddr, String>,
+    sort_by: SortBy,
+) -> Vec<(&'a IpAddr, &'a NetworkData)> {
+    let mut remote_addresses_list = Vec::from_iter(remote_addresses);
+    sort_list(&mut remote_addresses_list, sort_by, |remote_address, _| {
+        display_ip_or_host(**remote_address, ip_to_host)
+    });
+    remote_addresses_list
+}
+
+pub fn sort_connections<'a>(
+    connections: &'a BTreeMap<Connection, ConnectionData>,
+    ip_to_host: &HashMap<IpAddr, String>,
+    sort_by: SortBy,
+) -> Vec<(&'a Connection, &'a ConnectionData)> {
+    let mut connections_list = Vec::from_iter(connections);
+    sort_list(
+        &mut connections_list,
+        sort_by,
+        |connection, connection_data| {
+            display_connection_string(connection, ip_to_host, &connection_data.interface_name)
+        },
+    );
+    connections_list
+}
@@ -1,16 +1,21 @@
-use ::std::collections::HashMap;
+use ::std::collections::{HashMap, HashSet};
 
+use ::termion::event::Key;
 use ::tui::backend::Backend;
 use ::tui::Terminal;
 
-use crate::display::components::{HelpText, Layout, Table, TotalBandwidth};
-use crate::display::UIState;
+use crate::display::components::{
+    DetailPane, DisplayBytes, HelpText, Layout, ProcessView, Table, TotalBandwidth,
+};
+use crate::display::{
+    csv_rows, json_rows, sort_connections, sort_processes, sort_remote_addresses, sort_users,
+    Filter, JsonLine, ProcessKey, RowKey, SortBy, UIState, CSV_HEADER, JSON_SCHEMA_VERSION,
+};
 use crate::network::{display_connection_string, display_ip_or_host, LocalSocket, Utilization};
 
 use ::std::net::IpAddr;
 
-use crate::RenderOpts;
-use chrono::prelude::*;
+use crate::{InterfaceCounters, ProcessInfo, RenderOpts};
 
 pub struct Ui<B>
 where
@@ -20,39 +25,178 @@ where
     state: UIState,
     ip_to_host: HashMap<IpAddr, String>,
     opts: RenderOpts,
+    csv_header_written: bool,
+    show_totals: bool,
+    sort_by: SortBy,
+    selection: Selection,
+    detail_open: bool,
+    filter: Option<Filter>,
+    filter_prompt: Option<String>,
+    filter_totals: bool,
+    group_by_name: bool,
+    process_tree: bool,
+    collapsed_processes: HashSet<u32>,
+    // the traffic is counted by the kernel for tcp connections, as packets could not be captured
+    counters_only: bool,
+}
+
+#[derive(Default)]
+struct Selection {
+    focused_table: Option<usize>,
+    selected_row: Option<RowKey>,
+    selected_index: usize,
+    scroll_offset: usize,
+    // the page size of every table in the last draw, in the order they were laid out
+    page_sizes: Vec<usize>,
+}
+
+impl Selection {
+    fn page_size(&self) -> usize {
+        self.focused_table
+            .and_then(|focused_table| self.page_sizes.get(focused_table))
+            .copied()
+            .unwrap_or(1)
+            .max(1)
+    }
+    // the selection follows its process, address or connection when the rows move around,
+    // if it is gone the row at the same position is selected instead
+    fn resolve(&mut self, tables: &mut [Table]) {
+        let table = match self
+            .focused_table
+            .and_then(|focused_table| tables.get_mut(focused_table))
+        {
+            Some(table) => table,
+            None => return,
+        };
+        let row_keys = table.row_keys();
+        if row_keys.is_empty() {
+            return;
+        }
+        let selected_index = self
+            .selected_row
+            .as_ref()
+            .and_then(|selected_row| row_keys.iter().position(|key| key == selected_row))
+            .unwrap_or_else(|| self.selected_index.min(row_keys.len() - 1));
+        self.selected_row = Some(row_keys[selected_index].clone());
+        self.selected_index = selected_index;
+        table.focus(selected_index, self.scroll_offset);
+    }
+    fn has_selected_row(&self) -> bool {
+        self.focused_table.is_some() && self.selected_row.is_some()
+    }
+    fn focus_next_table(&mut self) {
+        let table_count = self.page_sizes.len();
+        if table_count == 0 {
+            return;
+        }
+        self.focused_table = Some(match self.focused_table {
+            Some(focused_table) => (focused_table + 1) % table_count,
+            None => 0,
+        });
+        self.selected_row = None;
+        self.selected_index = 0;
+        self.scroll_offset = 0;
+    }
+    fn move_selection(&mut self, mut tables: Vec<Table>, distance: isize) {
+        if self.focused_table.is_none() {
+            self.focus_next_table();
+            return;
+        }
+        self.resolve(&mut tables);
+        let row_keys = match self
+            .focused_table
+            .and_then(|focused_table| tables.get(focused_table))
+        {
+            Some(table) if !table.row_keys().is_empty() => table.row_keys(),
+            _ => return,
+        };
+        let selected_index = (self.selected_index as isize + distance)
+            .max(0)
+            .min(row_keys.len() as isize - 1) as usize;
+        self.selected_row = Some(row_keys[selected_index].clone());
+        self.selected_index = selected_index;
+        let page_size = self.page_size();
+        self.scroll_offset = self
+            .scroll_offset
+            .min(selected_index)
+            .max((selected_index + 1).saturating_sub(page_size));
+    }
 }
 
 impl<B> Ui<B>
 where
     B: Backend,
 {
-    pub fn new(terminal_backend: B, opts: RenderOpts) -> Self {
+    pub fn new(
+        terminal_backend: B,
+        opts: RenderOpts,
+        raw_mode: bool,
+        counters_only: bool,
+        process_info_available: bool,
+    ) -> Self {
         let mut terminal = Terminal::new(terminal_backend).unwrap();
         terminal.clear().unwrap();
         terminal.hide_cursor().unwrap();
+        let sort_by = opts.sort.unwrap_or(SortBy::Bandwidth);
+        let mut state = UIState::default();
+        state.keep_history = !raw_mode;
+        state.process_info_available = process_info_available;
         Ui {
             terminal,
-            state: Default::default(),
+            state,
             ip_to_host: Default::default(),
             opts,
+            csv_header_written: false,
+            show_totals: false,
+            sort_by,
+            selection: Default::default(),
+            detail_open: false,
+            filter: None,
+            filter_prompt: None,
+            filter_totals: false,
+            group_by_name: false,
+            process_tree: false,
+            collapsed_processes: HashSet::new(),
+            counters_only,
         }
     }
-    pub fn output_text(&mut self, write_to_stdout: &mut (dyn FnMut(String) + Send)) {
+    pub fn output_text(
+        &mut self,
+        write_to_stdout: &mut (dyn FnMut(String) + Send),
+        timestamp: i64,
+    ) {
         let state = &self.state;
         let ip_to_host = &self.ip_to_host;
-        let local_time: DateTime<Local> = Local::now();
-        let timestamp = local_time.timestamp();
-        for (process, process_network_data) in &state.processes {
+        let sort_by = self.sort_by;
+        for (interface_name, drops) in &state.new_capture_drops {
+            write_to_stdout(format!(
+                "capture_drops: <{}> {} dropped packets: {}",
+                timestamp, interface_name, drops
+            ));
+        }
+        for (process, process_network_data) in sort_processes(&state.processes, sort_by) {
+            let process_info = state.get_process_info(process);
+            // the fields added over time follow the original ones, and the command line comes last,
+            // as it may well contain quotes and spaces itself
             write_to_stdout(format!(
-                "process: <{}> \"{}\" up/down Bps: {}/{} connections: {}",
+                "process: <{}> \"{}\" up/down Bps: {}/{} connections: {} pid: {} user: \"{}\" exe: \"{}\" cmdline: \"{}\"",
                 timestamp,
-                process,
+                process.name,
                 process_network_data.total_bytes_uploaded,
                 process_network_data.total_bytes_downloaded,
-                process_network_data.connection_count
+                process_network_data.connection_count,
+                process
+                    .pid
+                    .map(|pid| pid.to_string())
+                    .unwrap_or_else(|| String::from("-")),
+                process_info.map(|process_info| process_info.user.as_str()).unwrap_or(""),
+                process_info.map(|process_info| process_info.exe.as_str()).unwrap_or(""),
+                process_info.map(|process_info| process_info.cmdline.as_str()).unwrap_or("")
             ));
         }
-        for (connection, connection_network_data) in &state.connections {
+        for (connection, connection_network_data) in
+            sort_connections(&state.connections, ip_to_host, sort_by)
+        {
             write_to_stdout(format!(
                 "connection: <{}> {} up/down Bps: {}/{} process: \"{}\"",
                 timestamp,
@@ -63,10 +207,12 @@ where
                 ),
                 connection_network_data.total_bytes_uploaded,
                 connection_network_data.total_bytes_downloaded,
-                connection_network_data.process_name
+                connection_network_data.process.name
             ));
         }
-        for (remote_address, remote_address_network_data) in &state.remote_addresses {
+        for (remote_address, remote_address_network_data) in
+            sort_remote_addresses(&state.remote_addresses, ip_to_host, sort_by)
+        {
             write_to_stdout(format!(
                 "remote_address: <{}> {} up/down Bps: {}/{} connections: {}",
                 timestamp,
@@ -76,62 +222,314 @@ where
                 remote_address_network_data.connection_count
             ));
         }
+        for (user, user_network_data) in sort_users(&state.users, sort_by) {
+            write_to_stdout(format!(
+                "user: <{}> \"{}\" up/down Bps: {}/{} connections: {}",
+                timestamp,
+                user,
+                user_network_data.total_bytes_uploaded,
+                user_network_data.total_bytes_downloaded,
+                user_network_data.connection_count
+            ));
+        }
+    }
+    pub fn output_json(
+        &mut self,
+        write_to_stdout: &mut (dyn FnMut(String) + Send),
+        timestamp: i64,
+    ) {
+        for row in json_rows(&self.state, &self.ip_to_host, self.sort_by) {
+            let line = JsonLine {
+                version: JSON_SCHEMA_VERSION,
+                timestamp,
+                row,
+            };
+            write_to_stdout(serde_json::to_string(&line).unwrap());
+        }
+    }
+    pub fn output_csv(&mut self, write_to_stdout: &mut (dyn FnMut(String) + Send), timestamp: i64) {
+        if !self.csv_header_written {
+            write_to_stdout(CSV_HEADER.to_string());
+            self.csv_header_written = true;
+        }
+        for row in csv_rows(&self.state, &self.ip_to_host, self.sort_by, timestamp) {
+            write_to_stdout(row);
+        }
+    }
+    // the traffic counted since the start, by process, for when the command run after -- is over
+    pub fn command_summary(&self) -> Vec<String> {
+        let state = &self.state;
+        let mut lines = vec![format!(
+            "Total Up / Down: {} / {}",
+            DisplayBytes(state.cumulative_bytes_uploaded as f64),
+            DisplayBytes(state.cumulative_bytes_downloaded as f64)
+        )];
+        for (process, process_network_data) in
+            sort_processes(&state.cumulative_processes, self.sort_by)
+        {
+            lines.push(format!(
+                "  {} (pid {}): {} / {} in {} connections",
+                process.name,
+                process
+                    .pid
+                    .map(|pid| pid.to_string())
+                    .unwrap_or_else(|| String::from("-")),
+                DisplayBytes(process_network_data.total_bytes_uploaded as f64),
+                DisplayBytes(process_network_data.total_bytes_downloaded as f64),
+                process_network_data.connection_count
+            ));
+        }
+        lines
     }
     pub fn draw(&mut self, paused: bool) {
+        // the state is filtered once for the tables and the header alike
+        let filtered_state = self.filtered_state();
+        let mut children = self.get_tables_to_display(filtered_state.as_ref());
+        self.selection.resolve(&mut children);
         let state = &self.state;
-        let children = self.get_tables_to_display();
+        let filtered_state = filtered_state.filter(|_| self.filter_totals);
+        let show_totals = self.show_totals;
+        let counters_only = self.counters_only;
+        let detail = match &self.selection.selected_row {
+            Some(selected_row) if self.detail_open => {
+                Some(DetailPane::new(selected_row, state, &self.ip_to_host))
+            }
+            _ => None,
+        };
+        let filter = self.filter.as_ref().map(|filter| filter.pattern.clone());
+        let filter_prompt = self.filter_prompt.clone();
+        let mut page_sizes = Vec::new();
         self.terminal
             .draw(|mut frame| {
                 let size = frame.size();
                 let total_bandwidth = TotalBandwidth {
-                    state: &state,
+                    state: filtered_state.as_ref().unwrap_or(state),
                     paused,
+                    show_totals,
+                    filtered: filtered_state.is_some(),
+                    counters_only,
+                };
+                let help_text = HelpText {
+                    paused,
+                    filter,
+                    filter_prompt,
                 };
-                let help_text = HelpText { paused };
                 let layout = Layout {
                     header: total_bandwidth,
                     children,
+                    detail,
                     footer: help_text,
                 };
-                layout.render(&mut frame, size);
+                page_sizes = layout.render(&mut frame, size);
             })
             .unwrap();
+        if !self.detail_open {
+            self.selection.page_sizes = page_sizes;
+        }
+    }
+    fn filtered_state(&self) -> Option<UIState> {
+        self.filter
+            .as_ref()
+            .map(|filter| filter.apply(&self.state, &self.ip_to_host, self.show_totals))
     }
 
-    fn get_tables_to_display(&self) -> Vec<Table<'static>> {
+    fn get_tables_to_display(&self, filtered_state: Option<&UIState>) -> Vec<Table<'static>> {
+        let state = filtered_state.unwrap_or(&self.state);
         let opts = &self.opts;
+        let show_processes = state.process_info_available;
         let mut children: Vec<Table> = Vec::new();
-        if opts.processes {
-            children.push(Table::create_processes_table(&self.state));
+        if opts.processes && show_processes {
+            children.push(Table::create_processes_table(
+                state,
+                self.show_totals,
+                self.sort_by,
+                self.process_view(),
+            ));
         }
         if opts.addresses {
             children.push(Table::create_remote_addresses_table(
-                &self.state,
+                state,
                 &self.ip_to_host,
+                self.show_totals,
+                self.sort_by,
             ));
         }
         if opts.connections {
             children.push(Table::create_connections_table(
-                &self.state,
+                state,
                 &self.ip_to_host,
+                self.show_totals,
+                self.sort_by,
             ));
         }
-        if !(opts.processes || opts.addresses || opts.connections) {
-            children = vec![
-                Table::create_processes_table(&self.state),
-                Table::create_remote_addresses_table(&self.state, &self.ip_to_host),
-                Table::create_connections_table(&self.state, &self.ip_to_host),
-            ];
+        if opts.users && show_processes {
+            children.push(Table::create_users_table(
+                state,
+                self.show_totals,
+                self.sort_by,
+            ));
+        }
+        if opts.containers && show_processes {
+            children.push(Table::create_containers_table(
+                state,
+                self.show_totals,
+                self.sort_by,
+            ));
+        }
+        if opts.units && show_processes {
+            children.push(Table::create_units_table(
+                state,
+                self.show_totals,
+                self.sort_by,
+            ));
+        }
+        if opts.interfaces {
+            children.push(Table::create_interfaces_table(
+                state,
+                self.show_totals,
+                self.sort_by,
+            ));
+        }
+        if children.is_empty() {
+            if show_processes {
+                children.push(Table::create_processes_table(
+                    state,
+                    self.show_totals,
+                    self.sort_by,
+                    self.process_view(),
+                ));
+            }
+            children.push(Table::create_remote_addresses_table(
+                state,
+                &self.ip_to_host,
+                self.show_totals,
+                self.sort_by,
+            ));
+            children.push(Table::create_connections_table(
+                state,
+                &self.ip_to_host,
+                self.show_totals,
+                self.sort_by,
+            ));
         }
         children
     }
+    // returns whether the key was handled, in which case the ui should be redrawn
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        if self.is_prompting() {
+            return self.handle_filter_prompt_key(key);
+        }
+        match key {
+            Key::Char('/') => self.filter_prompt = Some(String::new()),
+            Key::Char('f') if self.filter.is_some() => self.filter_totals = !self.filter_totals,
+            Key::Char('s') => self.sort_by = self.sort_by.next(),
+            Key::Char('b') => self.sort_by = SortBy::Bandwidth,
+            Key::Char('u') => self.sort_by = SortBy::Upload,
+            Key::Char('d') => self.sort_by = SortBy::Download,
+            Key::Char('+') => self.sort_by = SortBy::Total,
+            Key::Char('c') => self.sort_by = SortBy::Connections,
+            Key::Char('n') => self.sort_by = SortBy::Name,
+            Key::Char('t') => self.show_totals = !self.show_totals,
+            Key::Char('g') => {
+                self.group_by_name = !self.group_by_name;
+                self.process_tree = false;
+            }
+            Key::Char('p') => {
+                self.process_tree = !self.process_tree;
+                self.group_by_name = false;
+            }
+            Key::Left | Key::Right if self.process_tree => {
+                let pid = match &self.selection.selected_row {
+                    Some(RowKey::Process(ProcessKey { pid: Some(pid), .. }))
+                        if self.selection.has_selected_row() =>
+                    {
+                        *pid
+                    }
+                    _ => return false,
+                };
+                if key == Key::Left {
+                    self.collapsed_processes.insert(pid);
+                } else {
+                    self.collapsed_processes.remove(&pid);
+                }
+            }
+            Key::Char('\t') => {
+                self.detail_open = false;
+                self.selection.focus_next_table();
+            }
+            Key::Char('\n') if self.selection.has_selected_row() => self.detail_open = true,
+            Key::Esc if self.detail_open => self.detail_open = false,
+            Key::Esc => self.selection.focused_table = None,
+            Key::Up => self.move_selection(-1),
+            Key::Down => self.move_selection(1),
+            Key::PageUp => self.move_selection(-(self.selection.page_size() as isize)),
+            Key::PageDown => self.move_selection(self.selection.page_size() as isize),
+            _ => return false,
+        }
+        true
+    }
+    fn process_view(&self) -> ProcessView {
+        if self.process_tree {
+            ProcessView::Tree(&self.collapsed_processes)
+        } else if self.group_by_name {
+            ProcessView::Name
+        } else {
+            ProcessView::Pid
+        }
+    }
+    // while the filter prompt is open, keys are typed into it rather than acted on
+    pub fn is_prompting(&self) -> bool {
+        self.filter_prompt.is_some()
+    }
+    // an empty pattern clears the filter
+    fn handle_filter_prompt_key(&mut self, key: Key) -> bool {
+        match key {
+            Key::Char('\n') => {
+                let pattern = self.filter_prompt.take().unwrap_or_default();
+                self.filter = if pattern.is_empty() {
+                    None
+                } else {
+                    Some(Filter::new(&pattern))
+                };
+            }
+            Key::Esc => self.filter_prompt = None,
+            Key::Backspace => {
+                if let Some(filter_prompt) = self.filter_prompt.as_mut() {
+                    filter_prompt.pop();
+                }
+            }
+            Key::Char(character) => {
+                if let Some(filter_prompt) = self.filter_prompt.as_mut() {
+                    filter_prompt.push(character);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+    fn move_selection(&mut self, distance: isize) {
+        let tables = self.get_tables_to_display(self.filtered_state().as_ref());
+        self.selection.move_selection(tables, distance);
+    }
     pub fn update_state(
         &mut self,
-        connections_to_procs: HashMap<LocalSocket, String>,
+        connections_to_procs: HashMap<LocalSocket, ProcessInfo>,
         utilization: Utilization,
-        ip_to_host: HashMap<IpAddr, String>,
+        interface_counters: HashMap<String, InterfaceCounters>,
+        capture_drops: HashMap<String, u64>,
+        timestamp: i64,
+        paused: bool,
     ) {
-        self.state.update(connections_to_procs, utilization);
+        self.state.update(
+            connections_to_procs,
+            utilization,
+            interface_counters,
+            capture_drops,
+            timestamp,
+            paused,
+        );
+    }
+    pub fn update_ip_to_host(&mut self, ip_to_host: HashMap<IpAddr, String>) {
         self.ip_to_host.extend(ip_to_host);
     }
     pub fn end(&mut self) {
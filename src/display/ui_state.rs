@@ -1,30 +1,62 @@
-use ::std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
-use ::std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use ::std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use ::std::net::IpAddr;
 
-use crate::network::{Connection, LocalSocket, Utilization};
+use crate::network::{get_socket_owner, Connection, ConnectionInfo, LocalSocket, Utilization};
+use crate::{Container, InterfaceCounters, ProcessInfo};
 
 static RECALL_LENGTH: usize = 5;
+static HISTORY_LENGTH: usize = 120;
+// the rows of each kind kept in the totals, those that were idle the longest are forgotten past it
+static MAX_CUMULATIVE_ROWS: usize = 10_000;
 
 pub trait Bandwidth {
     fn get_total_bytes_downloaded(&self) -> u128;
     fn get_total_bytes_uploaded(&self) -> u128;
+    fn get_connection_count(&self) -> u128;
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct NetworkData {
     pub total_bytes_downloaded: u128,
     pub total_bytes_uploaded: u128,
     pub connection_count: u128,
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct ConnectionData {
     pub total_bytes_downloaded: u128,
     pub total_bytes_uploaded: u128,
-    pub process_name: String,
+    pub process: ProcessKey,
+    pub user: String,
+    pub container: ContainerKey,
+    pub unit: String,
     pub interface_name: String,
 }
 
+// the traffic of an interface as counted by the kernel, next to what was sniffed on it. Errors and
+// drops are counted since the first update, the kernel counters are not known for interfaces that
+// were only sniffed on (eg. those of a capture file)
+#[derive(Clone, Default)]
+pub struct InterfaceData {
+    pub counted: bool,
+    pub total_bytes_downloaded: u128,
+    pub total_bytes_uploaded: u128,
+    pub packets: u128,
+    pub errors: u64,
+    pub drops: u64,
+    pub speed: Option<u64>,
+    pub sniffed: NetworkData,
+}
+
+impl InterfaceData {
+    // the busier direction as a share of the link speed, links being full duplex
+    pub fn link_utilization(&self) -> Option<f64> {
+        let speed = self.speed.filter(|_| self.counted)?;
+        let bits = self.total_bytes_uploaded.max(self.total_bytes_downloaded) as f64 * 8.0;
+        Some(bits / (speed as f64 * 1_000_000.0) * 100.0)
+    }
+}
+
 impl NetworkData {
     pub fn divide_by(&mut self, amount: u128) {
         self.total_bytes_downloaded /= amount;
@@ -46,6 +78,21 @@ impl Bandwidth for ConnectionData {
     fn get_total_bytes_downloaded(&self) -> u128 {
         self.total_bytes_downloaded
     }
+    fn get_connection_count(&self) -> u128 {
+        1
+    }
+}
+
+impl Bandwidth for InterfaceData {
+    fn get_total_bytes_uploaded(&self) -> u128 {
+        self.total_bytes_uploaded
+    }
+    fn get_total_bytes_downloaded(&self) -> u128 {
+        self.total_bytes_downloaded
+    }
+    fn get_connection_count(&self) -> u128 {
+        self.sniffed.connection_count
+    }
 }
 
 impl Bandwidth for NetworkData {
@@ -55,49 +102,624 @@ impl Bandwidth for NetworkData {
     fn get_total_bytes_downloaded(&self) -> u128 {
         self.total_bytes_downloaded
     }
+    fn get_connection_count(&self) -> u128 {
+        self.connection_count
+    }
+}
+
+// a process, or all the processes of a name when they are grouped by name
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Default)]
+pub struct ProcessKey {
+    pub name: String,
+    pub pid: Option<u32>,
+}
+
+impl ProcessKey {
+    pub fn unknown() -> Self {
+        ProcessKey {
+            name: String::from("<UNKNOWN>"),
+            pid: None,
+        }
+    }
+    pub fn grouped_by_name(&self) -> Self {
+        ProcessKey {
+            name: self.name.clone(),
+            pid: None,
+        }
+    }
+}
+
+impl From<&ProcessInfo> for ProcessKey {
+    fn from(process_info: &ProcessInfo) -> Self {
+        ProcessKey {
+            name: process_info.name.clone(),
+            pid: Some(process_info.pid),
+        }
+    }
+}
+
+// the connections of processes with the same name are counted together
+pub fn group_processes_by_name(
+    processes: &BTreeMap<ProcessKey, NetworkData>,
+) -> BTreeMap<ProcessKey, NetworkData> {
+    let mut processes_by_name: BTreeMap<ProcessKey, NetworkData> = BTreeMap::new();
+    for (process, network_data) in processes {
+        let data_for_name = processes_by_name
+            .entry(process.grouped_by_name())
+            .or_default();
+        data_for_name.total_bytes_downloaded += network_data.total_bytes_downloaded;
+        data_for_name.total_bytes_uploaded += network_data.total_bytes_uploaded;
+        data_for_name.connection_count += network_data.connection_count;
+    }
+    processes_by_name
+}
+
+// the container of the process of a connection, the host when it is not in one
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Default)]
+pub enum ContainerKey {
+    Container(Container),
+    Host,
+    #[default]
+    Unknown,
+}
+
+impl ContainerKey {
+    fn of(process_info: Option<&ProcessInfo>) -> Self {
+        match process_info {
+            Some(ProcessInfo {
+                container: Some(container),
+                ..
+            }) => ContainerKey::Container(container.clone()),
+            Some(_) => ContainerKey::Host,
+            None => ContainerKey::Unknown,
+        }
+    }
+    // container ids are shortened the way docker and crictl do
+    pub fn short_name(&self) -> String {
+        match self {
+            ContainerKey::Container(container) => container.id.chars().take(12).collect(),
+            ContainerKey::Host => String::from("<HOST>"),
+            ContainerKey::Unknown => String::from("<UNKNOWN>"),
+        }
+    }
+}
+
+// processes along with their ancestors, the traffic of each one rolled up with that of its
+// descendants
+#[derive(Default)]
+pub struct ProcessTree {
+    pub rolled_up: BTreeMap<ProcessKey, NetworkData>,
+    // the children of each process by its pid, the roots of the tree are the children of None
+    pub children: HashMap<Option<u32>, BTreeSet<ProcessKey>>,
+}
+
+impl ProcessTree {
+    pub fn new(
+        processes: &BTreeMap<ProcessKey, NetworkData>,
+        process_info: &HashMap<u32, ProcessInfo>,
+    ) -> Self {
+        let mut tree = ProcessTree::default();
+        for (process, network_data) in processes {
+            let ancestors = process
+                .pid
+                .and_then(|pid| process_info.get(&pid))
+                .map(|process_info| process_info.ancestors.as_slice())
+                .unwrap_or_default();
+            let mut child = process.clone();
+            tree.add(&child, network_data);
+            for (pid, name) in ancestors {
+                let parent = ProcessKey {
+                    name: name.clone(),
+                    pid: Some(*pid),
+                };
+                tree.add(&parent, network_data);
+                tree.children.entry(Some(*pid)).or_default().insert(child);
+                child = parent;
+            }
+            tree.children.entry(None).or_default().insert(child);
+        }
+        tree
+    }
+    fn add(&mut self, process: &ProcessKey, network_data: &NetworkData) {
+        let data_for_process = self.rolled_up.entry(process.clone()).or_default();
+        data_for_process.total_bytes_downloaded += network_data.total_bytes_downloaded;
+        data_for_process.total_bytes_uploaded += network_data.total_bytes_uploaded;
+        data_for_process.connection_count += network_data.connection_count;
+    }
+}
+
+// the systemd unit of the process of a connection, where there is one
+fn unit_of(process_info: Option<&ProcessInfo>) -> String {
+    process_info
+        .map(|process_info| process_info.unit.clone())
+        .filter(|unit| !unit.is_empty())
+        .unwrap_or_else(|| String::from("<UNKNOWN>"))
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum RowKey {
+    Process(ProcessKey),
+    User(String),
+    Container(ContainerKey),
+    Unit(String),
+    Interface(String),
+    RemoteAddress(IpAddr),
+    Connection(Connection),
+}
+
+pub struct History {
+    pub first_seen: i64,
+    // bytes uploaded and downloaded in each of the last HISTORY_LENGTH updates, oldest first
+    pub bytes: VecDeque<(u128, u128)>,
 }
 
 pub struct UtilizationData {
-    connections_to_procs: HashMap<LocalSocket, String>,
+    connections_to_procs: HashMap<LocalSocket, ProcessInfo>,
     network_utilization: Utilization,
 }
 
+// the traffic of some connections added up by process, user, container, systemd unit and remote
+// address, and in total
+#[derive(Default)]
+struct AggregatedConnections {
+    processes: BTreeMap<ProcessKey, NetworkData>,
+    users: BTreeMap<String, NetworkData>,
+    containers: BTreeMap<ContainerKey, NetworkData>,
+    units: BTreeMap<String, NetworkData>,
+    remote_addresses: BTreeMap<IpAddr, NetworkData>,
+    total_bytes_uploaded: u128,
+    total_bytes_downloaded: u128,
+}
+
+fn aggregate_connections<'a>(
+    connections: impl IntoIterator<Item = (&'a Connection, &'a ConnectionData)>,
+    process_info_available: bool,
+) -> AggregatedConnections {
+    let mut processes: BTreeMap<ProcessKey, NetworkData> = BTreeMap::new();
+    let mut users: BTreeMap<String, NetworkData> = BTreeMap::new();
+    let mut containers: BTreeMap<ContainerKey, NetworkData> = BTreeMap::new();
+    let mut units: BTreeMap<String, NetworkData> = BTreeMap::new();
+    let mut remote_addresses: BTreeMap<IpAddr, NetworkData> = BTreeMap::new();
+    let mut total_bytes_uploaded = 0;
+    let mut total_bytes_downloaded = 0;
+    for (connection, connection_data) in connections {
+        let mut data_for_keys = vec![remote_addresses
+            .entry(connection.remote_socket.ip)
+            .or_default()];
+        if process_info_available {
+            data_for_keys.push(
+                processes
+                    .entry(connection_data.process.clone())
+                    .or_default(),
+            );
+            data_for_keys.push(users.entry(connection_data.user.clone()).or_default());
+            data_for_keys.push(
+                containers
+                    .entry(connection_data.container.clone())
+                    .or_default(),
+            );
+            data_for_keys.push(units.entry(connection_data.unit.clone()).or_default());
+        }
+        for network_data in data_for_keys {
+            network_data.total_bytes_uploaded += connection_data.total_bytes_uploaded;
+            network_data.total_bytes_downloaded += connection_data.total_bytes_downloaded;
+            network_data.connection_count += 1;
+        }
+        total_bytes_uploaded += connection_data.total_bytes_uploaded;
+        total_bytes_downloaded += connection_data.total_bytes_downloaded;
+    }
+    AggregatedConnections {
+        processes,
+        users,
+        containers,
+        units,
+        remote_addresses,
+        total_bytes_uploaded,
+        total_bytes_downloaded,
+    }
+}
+
+// what the counters of each interface grew by from the earlier to the later ones, divided by the
+// number of ticks in between
+fn count_interfaces(
+    later: &HashMap<String, InterfaceCounters>,
+    earlier: &HashMap<String, InterfaceCounters>,
+    first: &HashMap<String, InterfaceCounters>,
+    ticks: u128,
+) -> BTreeMap<String, InterfaceData> {
+    later
+        .iter()
+        .map(|(interface_name, counters)| {
+            let since = |counters_at: &HashMap<String, InterfaceCounters>| {
+                counters_at.get(interface_name).cloned().unwrap_or_default()
+            };
+            let (earlier, first) = (since(earlier), since(first));
+            // the counters start over when an interface is recreated
+            let delta = |later: u64, earlier: u64| later.saturating_sub(earlier) as u128 / ticks;
+            let interface_data = InterfaceData {
+                counted: true,
+                total_bytes_downloaded: delta(counters.bytes_received, earlier.bytes_received),
+                total_bytes_uploaded: delta(counters.bytes_sent, earlier.bytes_sent),
+                packets: delta(counters.packets_received, earlier.packets_received)
+                    + delta(counters.packets_sent, earlier.packets_sent),
+                errors: counters.errors.saturating_sub(first.errors),
+                drops: counters.drops.saturating_sub(first.drops),
+                speed: counters.speed,
+                sniffed: NetworkData::default(),
+            };
+            (interface_name.clone(), interface_data)
+        })
+        .collect()
+}
+
+// the traffic sniffed on each interface by the connections
+fn sniff_interfaces<'a>(
+    connections: impl IntoIterator<Item = &'a ConnectionData>,
+) -> BTreeMap<String, NetworkData> {
+    let mut sniffed: BTreeMap<String, NetworkData> = BTreeMap::new();
+    for connection_data in connections {
+        let data_for_interface = sniffed
+            .entry(connection_data.interface_name.clone())
+            .or_default();
+        data_for_interface.total_bytes_uploaded += connection_data.total_bytes_uploaded;
+        data_for_interface.total_bytes_downloaded += connection_data.total_bytes_downloaded;
+        data_for_interface.connection_count += 1;
+    }
+    sniffed
+}
+
+// the interfaces along with the traffic sniffed on each of them
+fn add_sniffed_traffic(
+    interfaces: &BTreeMap<String, InterfaceData>,
+    sniffed: &BTreeMap<String, NetworkData>,
+) -> BTreeMap<String, InterfaceData> {
+    let mut interfaces = interfaces.clone();
+    for interface_data in interfaces.values_mut() {
+        interface_data.sniffed = NetworkData::default();
+    }
+    for (interface_name, network_data) in sniffed {
+        interfaces
+            .entry(interface_name.clone())
+            .or_default()
+            .sniffed = network_data.clone();
+    }
+    interfaces
+}
+
+// the rows past MAX_CUMULATIVE_ROWS that were idle the longest, down to a tenth below it so that
+// they are not sorted again on every update
+fn forget_idle_rows<K: Ord + Clone, V>(
+    rows: &mut BTreeMap<K, V>,
+    last_seen: &mut HashMap<RowKey, u64>,
+    row_key: impl Fn(&K) -> RowKey,
+) {
+    if rows.len() <= MAX_CUMULATIVE_ROWS {
+        return;
+    }
+    let mut idle_rows: Vec<(u64, K)> = rows
+        .keys()
+        .map(|key| {
+            (
+                last_seen.get(&row_key(key)).cloned().unwrap_or(0),
+                key.clone(),
+            )
+        })
+        .collect();
+    idle_rows.sort_by_key(|(tick, _)| *tick);
+    let excess = rows.len() - MAX_CUMULATIVE_ROWS * 9 / 10;
+    for (_, key) in idle_rows.into_iter().take(excess) {
+        rows.remove(&key);
+        last_seen.remove(&row_key(&key));
+    }
+}
+
 #[derive(Default)]
 pub struct UIState {
-    pub processes: BTreeMap<String, NetworkData>,
+    pub processes: BTreeMap<ProcessKey, NetworkData>,
+    pub users: BTreeMap<String, NetworkData>,
+    pub containers: BTreeMap<ContainerKey, NetworkData>,
+    pub units: BTreeMap<String, NetworkData>,
+    pub interfaces: BTreeMap<String, InterfaceData>,
     pub remote_addresses: BTreeMap<IpAddr, NetworkData>,
     pub connections: BTreeMap<Connection, ConnectionData>,
     pub total_bytes_downloaded: u128,
     pub total_bytes_uploaded: u128,
+    // whether the sockets of the traffic can be polled for their processes, which they cannot when
+    // it is read from a capture
+    pub process_info_available: bool,
+    pub cumulative_processes: BTreeMap<ProcessKey, NetworkData>,
+    pub cumulative_users: BTreeMap<String, NetworkData>,
+    pub cumulative_containers: BTreeMap<ContainerKey, NetworkData>,
+    pub cumulative_units: BTreeMap<String, NetworkData>,
+    pub cumulative_interfaces: BTreeMap<String, InterfaceData>,
+    pub cumulative_remote_addresses: BTreeMap<IpAddr, NetworkData>,
+    pub cumulative_connections: BTreeMap<Connection, ConnectionData>,
+    pub cumulative_bytes_downloaded: u128,
+    pub cumulative_bytes_uploaded: u128,
+    // the traffic sniffed on each interface since the start, kept apart from the connections as
+    // those can be forgotten
+    cumulative_sniffed: BTreeMap<String, NetworkData>,
+    // the update each row of the totals last had traffic in, to forget the idle ones first
+    ticks: u64,
+    last_seen: HashMap<RowKey, u64>,
+    pub history: HashMap<RowKey, History>,
+    // only the detail pane reads the history, which the raw output has no use for
+    pub keep_history: bool,
+    // the packets the kernel dropped on each interface since the start and during the last update,
+    // which were never counted
+    pub capture_drops: BTreeMap<String, u64>,
+    pub new_capture_drops: BTreeMap<String, u64>,
+    // the packets dropped during each of the last RECALL_LENGTH updates, which the rates miss
+    recent_capture_drops: VecDeque<u64>,
+    // the processes of the rows by pid, so the rows of processes that are gone can still be described
+    pub process_info: HashMap<u32, ProcessInfo>,
     utilization_data: VecDeque<UtilizationData>,
+    // the counters of the interfaces at the first update and at the last RECALL_LENGTH ones, plus
+    // the one before to count them from
+    first_interface_counters: HashMap<String, InterfaceCounters>,
+    interface_counters: VecDeque<HashMap<String, InterfaceCounters>>,
 }
 
 impl UIState {
-    fn get_proc_name<'a>(
-        connections_to_procs: &'a HashMap<LocalSocket, String>,
-        local_socket: &LocalSocket,
-    ) -> Option<&'a String> {
-        if let Some(process_name) = connections_to_procs.get(local_socket) {
-            Some(process_name)
-        } else if let Some(process_name) = connections_to_procs.get(&LocalSocket {
-            ip: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
-            port: local_socket.port,
-            protocol: local_socket.protocol,
-        }) {
-            Some(process_name)
+    // a process grouped by name has no single process to describe
+    pub fn get_process_info(&self, process: &ProcessKey) -> Option<&ProcessInfo> {
+        process.pid.and_then(|pid| self.process_info.get(&pid))
+    }
+    fn update_history(&mut self, bytes: HashMap<RowKey, (u128, u128)>, timestamp: i64) {
+        for (key, history) in self.history.iter_mut() {
+            history
+                .bytes
+                .push_back(bytes.get(key).cloned().unwrap_or_default());
+            if history.bytes.len() > HISTORY_LENGTH {
+                history.bytes.pop_front();
+            }
+        }
+        // a row without traffic over the whole history is forgotten, so that the rows of every
+        // connection ever seen do not pile up
+        self.history.retain(|_, history| {
+            history.bytes.len() < HISTORY_LENGTH
+                || history.bytes.iter().any(|&(up, down)| up > 0 || down > 0)
+        });
+        for (key, key_bytes) in bytes {
+            self.history.entry(key).or_insert_with(|| History {
+                first_seen: timestamp,
+                bytes: vec![key_bytes].into_iter().collect(),
+            });
+        }
+    }
+    fn update_cumulative(
+        &mut self,
+        connections_to_procs: &HashMap<LocalSocket, ProcessInfo>,
+        network_utilization: &Utilization,
+        timestamp: i64,
+    ) {
+        let mut bytes: HashMap<RowKey, (u128, u128)> = HashMap::new();
+        let mut add_bytes = |key: RowKey, connection_info: &ConnectionInfo| {
+            let key_bytes = bytes.entry(key).or_default();
+            key_bytes.0 += connection_info.total_bytes_uploaded;
+            key_bytes.1 += connection_info.total_bytes_downloaded;
+        };
+        self.ticks += 1;
+        for (connection, connection_info) in &network_utilization.connections {
+            add_bytes(RowKey::Connection(*connection), connection_info);
+            add_bytes(
+                RowKey::Interface(connection_info.interface_name.clone()),
+                connection_info,
+            );
+            add_bytes(
+                RowKey::RemoteAddress(connection.remote_socket.ip),
+                connection_info,
+            );
+            // a connection that was forgotten is counted again when it comes back
+            let connection_previously_seen = self.cumulative_connections.contains_key(connection);
+            self.last_seen
+                .insert(RowKey::Connection(*connection), self.ticks);
+            self.last_seen.insert(
+                RowKey::RemoteAddress(connection.remote_socket.ip),
+                self.ticks,
+            );
+            let data_for_interface = self
+                .cumulative_sniffed
+                .entry(connection_info.interface_name.clone())
+                .or_default();
+            data_for_interface.total_bytes_downloaded += connection_info.total_bytes_downloaded;
+            data_for_interface.total_bytes_uploaded += connection_info.total_bytes_uploaded;
+            if !connection_previously_seen {
+                data_for_interface.connection_count += 1;
+            }
+            let connection_data = self.cumulative_connections.entry(*connection).or_default();
+            let data_for_remote_address = self
+                .cumulative_remote_addresses
+                .entry(connection.remote_socket.ip)
+                .or_default();
+            connection_data.total_bytes_downloaded += connection_info.total_bytes_downloaded;
+            connection_data.total_bytes_uploaded += connection_info.total_bytes_uploaded;
+            connection_data.interface_name = connection_info.interface_name.clone();
+            data_for_remote_address.total_bytes_downloaded +=
+                connection_info.total_bytes_downloaded;
+            data_for_remote_address.total_bytes_uploaded += connection_info.total_bytes_uploaded;
+            if !connection_previously_seen {
+                data_for_remote_address.connection_count += 1;
+            }
+            self.cumulative_bytes_downloaded += connection_info.total_bytes_downloaded;
+            self.cumulative_bytes_uploaded += connection_info.total_bytes_uploaded;
+
+            if !self.process_info_available {
+                continue;
+            }
+
+            let process_info = get_socket_owner(connections_to_procs, &connection.local_socket);
+            connection_data.process = process_info
+                .map(ProcessKey::from)
+                .unwrap_or_else(ProcessKey::unknown);
+            connection_data.user = process_info
+                .map(|process_info| process_info.user.clone())
+                .unwrap_or_else(|| String::from("<UNKNOWN>"));
+            connection_data.container = ContainerKey::of(process_info);
+            connection_data.unit = unit_of(process_info);
+            for key in [
+                RowKey::Process(connection_data.process.clone()),
+                RowKey::User(connection_data.user.clone()),
+                RowKey::Container(connection_data.container.clone()),
+                RowKey::Unit(connection_data.unit.clone()),
+            ] {
+                self.last_seen.insert(key, self.ticks);
+            }
+            add_bytes(
+                RowKey::Process(connection_data.process.clone()),
+                connection_info,
+            );
+            add_bytes(RowKey::User(connection_data.user.clone()), connection_info);
+            add_bytes(
+                RowKey::Container(connection_data.container.clone()),
+                connection_info,
+            );
+            add_bytes(RowKey::Unit(connection_data.unit.clone()), connection_info);
+            // the history is kept for the grouped rows as well, in case they are looked at
+            if connection_data.process.pid.is_some() {
+                add_bytes(
+                    RowKey::Process(connection_data.process.grouped_by_name()),
+                    connection_info,
+                );
+            }
+            let data_for_process = self
+                .cumulative_processes
+                .entry(connection_data.process.clone())
+                .or_default();
+            let data_for_user = self
+                .cumulative_users
+                .entry(connection_data.user.clone())
+                .or_default();
+            let data_for_container = self
+                .cumulative_containers
+                .entry(connection_data.container.clone())
+                .or_default();
+            let data_for_unit = self
+                .cumulative_units
+                .entry(connection_data.unit.clone())
+                .or_default();
+            for network_data in vec![
+                data_for_process,
+                data_for_user,
+                data_for_container,
+                data_for_unit,
+            ] {
+                network_data.total_bytes_downloaded += connection_info.total_bytes_downloaded;
+                network_data.total_bytes_uploaded += connection_info.total_bytes_uploaded;
+                if !connection_previously_seen {
+                    network_data.connection_count += 1;
+                }
+            }
+        }
+        if self.keep_history {
+            self.update_history(bytes, timestamp);
+        }
+        self.forget_idle_rows();
+    }
+    fn forget_idle_rows(&mut self) {
+        let last_seen = &mut self.last_seen;
+        forget_idle_rows(&mut self.cumulative_connections, last_seen, |connection| {
+            RowKey::Connection(*connection)
+        });
+        forget_idle_rows(&mut self.cumulative_remote_addresses, last_seen, |ip| {
+            RowKey::RemoteAddress(*ip)
+        });
+        forget_idle_rows(&mut self.cumulative_processes, last_seen, |process| {
+            RowKey::Process(process.clone())
+        });
+        forget_idle_rows(&mut self.cumulative_users, last_seen, |user| {
+            RowKey::User(user.clone())
+        });
+        forget_idle_rows(&mut self.cumulative_containers, last_seen, |container| {
+            RowKey::Container(container.clone())
+        });
+        forget_idle_rows(&mut self.cumulative_units, last_seen, |unit| {
+            RowKey::Unit(unit.clone())
+        });
+    }
+    // the history is not carried over, the detail pane reads it from the unfiltered state. Only the
+    // rates or the totals are filtered, whichever are shown
+    pub fn filtered(
+        &self,
+        matches: impl Fn(&Connection, &ConnectionData) -> bool,
+        show_totals: bool,
+    ) -> UIState {
+        let connections = if show_totals {
+            &self.cumulative_connections
+        } else {
+            &self.connections
+        };
+        let matching = || {
+            connections
+                .iter()
+                .filter(|(connection, connection_data)| matches(connection, connection_data))
+        };
+        let aggregated = aggregate_connections(matching(), self.process_info_available);
+        let interfaces = if show_totals {
+            &self.cumulative_interfaces
+        } else {
+            &self.interfaces
+        };
+        let interfaces = add_sniffed_traffic(
+            interfaces,
+            &sniff_interfaces(matching().map(|(_, connection_data)| connection_data)),
+        );
+        let connections = matching()
+            .map(|(connection, connection_data)| (*connection, connection_data.clone()))
+            .collect();
+        let mut state = UIState {
+            process_info_available: self.process_info_available,
+            process_info: self.process_info.clone(),
+            capture_drops: self.capture_drops.clone(),
+            recent_capture_drops: self.recent_capture_drops.clone(),
+            ..Default::default()
+        };
+        if show_totals {
+            state.cumulative_processes = aggregated.processes;
+            state.cumulative_users = aggregated.users;
+            state.cumulative_containers = aggregated.containers;
+            state.cumulative_units = aggregated.units;
+            state.cumulative_interfaces = interfaces;
+            state.cumulative_remote_addresses = aggregated.remote_addresses;
+            state.cumulative_connections = connections;
+            state.cumulative_bytes_downloaded = aggregated.total_bytes_downloaded;
+            state.cumulative_bytes_uploaded = aggregated.total_bytes_uploaded;
         } else {
-            connections_to_procs.get(&LocalSocket {
-                ip: IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0)),
-                port: local_socket.port,
-                protocol: local_socket.protocol,
-            })
+            state.processes = aggregated.processes;
+            state.users = aggregated.users;
+            state.containers = aggregated.containers;
+            state.units = aggregated.units;
+            state.interfaces = interfaces;
+            state.remote_addresses = aggregated.remote_addresses;
+            state.connections = connections;
+            state.total_bytes_downloaded = aggregated.total_bytes_downloaded;
+            state.total_bytes_uploaded = aggregated.total_bytes_uploaded;
         }
+        state
     }
     pub fn update(
         &mut self,
-        connections_to_procs: HashMap<LocalSocket, String>,
+        connections_to_procs: HashMap<LocalSocket, ProcessInfo>,
         network_utilization: Utilization,
+        interface_counters: HashMap<String, InterfaceCounters>,
+        capture_drops: HashMap<String, u64>,
+        timestamp: i64,
+        paused: bool,
     ) {
+        self.update_interfaces(interface_counters, paused);
+        self.update_capture_drops(capture_drops);
+        for process_info in connections_to_procs.values() {
+            self.process_info
+                .insert(process_info.pid, process_info.clone());
+        }
+        self.update_cumulative(&connections_to_procs, &network_utilization, timestamp);
+        if paused {
+            // the totals keep counting, while the rates stay as they were when paused
+            self.cumulative_interfaces =
+                add_sniffed_traffic(&self.cumulative_interfaces, &self.cumulative_sniffed);
+            self.prune_process_info();
+            return;
+        }
         self.utilization_data.push_back(UtilizationData {
             connections_to_procs,
             network_utilization,
@@ -105,7 +727,10 @@ impl UIState {
         if self.utilization_data.len() > RECALL_LENGTH {
             self.utilization_data.pop_front();
         }
-        let mut processes: BTreeMap<String, NetworkData> = BTreeMap::new();
+        let mut processes: BTreeMap<ProcessKey, NetworkData> = BTreeMap::new();
+        let mut users: BTreeMap<String, NetworkData> = BTreeMap::new();
+        let mut containers: BTreeMap<ContainerKey, NetworkData> = BTreeMap::new();
+        let mut units: BTreeMap<String, NetworkData> = BTreeMap::new();
         let mut remote_addresses: BTreeMap<IpAddr, NetworkData> = BTreeMap::new();
         let mut connections: BTreeMap<Connection, ConnectionData> = BTreeMap::new();
         let mut total_bytes_downloaded: u128 = 0;
@@ -134,22 +759,41 @@ impl UIState {
                 total_bytes_downloaded += connection_info.total_bytes_downloaded;
                 total_bytes_uploaded += connection_info.total_bytes_uploaded;
 
-                let data_for_process = if let Some(process_name) =
-                    UIState::get_proc_name(&connections_to_procs, &connection.local_socket)
-                {
-                    connection_data.process_name = process_name.clone();
-                    processes.entry(process_name.clone()).or_default()
-                } else {
-                    connection_data.process_name = String::from("<UNKNOWN>");
-                    processes
-                        .entry(connection_data.process_name.clone())
-                        .or_default()
-                };
+                if !self.process_info_available {
+                    // there is nothing to attribute the traffic to (eg. when reading a capture
+                    // from another machine), so it is left out rather than marked as <UNKNOWN>
+                    continue;
+                }
 
-                data_for_process.total_bytes_downloaded += connection_info.total_bytes_downloaded;
-                data_for_process.total_bytes_uploaded += connection_info.total_bytes_uploaded;
-                if !connection_previously_seen {
-                    data_for_process.connection_count += 1;
+                let process_info = get_socket_owner(connections_to_procs, &connection.local_socket);
+                connection_data.process = process_info
+                    .map(ProcessKey::from)
+                    .unwrap_or_else(ProcessKey::unknown);
+                connection_data.user = process_info
+                    .map(|process_info| process_info.user.clone())
+                    .unwrap_or_else(|| String::from("<UNKNOWN>"));
+                connection_data.container = ContainerKey::of(process_info);
+                connection_data.unit = unit_of(process_info);
+                let data_for_process = processes
+                    .entry(connection_data.process.clone())
+                    .or_default();
+                let data_for_user = users.entry(connection_data.user.clone()).or_default();
+                let data_for_container = containers
+                    .entry(connection_data.container.clone())
+                    .or_default();
+                let data_for_unit = units.entry(connection_data.unit.clone()).or_default();
+
+                for network_data in vec![
+                    data_for_process,
+                    data_for_user,
+                    data_for_container,
+                    data_for_unit,
+                ] {
+                    network_data.total_bytes_downloaded += connection_info.total_bytes_downloaded;
+                    network_data.total_bytes_uploaded += connection_info.total_bytes_uploaded;
+                    if !connection_previously_seen {
+                        network_data.connection_count += 1;
+                    }
                 }
             }
         }
@@ -161,6 +805,15 @@ impl UIState {
         for (_, network_data) in processes.iter_mut() {
             network_data.divide_by(divide_by)
         }
+        for (_, network_data) in users.iter_mut() {
+            network_data.divide_by(divide_by)
+        }
+        for (_, network_data) in containers.iter_mut() {
+            network_data.divide_by(divide_by)
+        }
+        for (_, network_data) in units.iter_mut() {
+            network_data.divide_by(divide_by)
+        }
         for (_, network_data) in remote_addresses.iter_mut() {
             network_data.divide_by(divide_by)
         }
@@ -168,9 +821,78 @@ impl UIState {
             connection_data.divide_by(divide_by)
         }
         self.processes = processes;
+        self.users = users;
+        self.containers = containers;
+        self.units = units;
         self.remote_addresses = remote_addresses;
+        self.interfaces =
+            add_sniffed_traffic(&self.interfaces, &sniff_interfaces(connections.values()));
+        self.cumulative_interfaces =
+            add_sniffed_traffic(&self.cumulative_interfaces, &self.cumulative_sniffed);
         self.connections = connections;
         self.total_bytes_downloaded = total_bytes_downloaded / divide_by;
         self.total_bytes_uploaded = total_bytes_uploaded / divide_by;
+        self.prune_process_info();
+    }
+    // every socket of the machine has a process, so only those of the rows are kept. The process of
+    // each connection has a row of its own, unless it was forgotten
+    fn prune_process_info(&mut self) {
+        let pids: HashSet<u32> = self
+            .processes
+            .keys()
+            .chain(self.cumulative_processes.keys())
+            .filter_map(|process| process.pid)
+            .collect();
+        self.process_info.retain(|pid, _| pids.contains(pid));
+    }
+    pub fn recent_capture_drops(&self) -> u64 {
+        self.recent_capture_drops.iter().sum()
+    }
+    fn update_capture_drops(&mut self, capture_drops: HashMap<String, u64>) {
+        self.new_capture_drops.clear();
+        for (interface_name, drops) in capture_drops {
+            let previous_drops = self
+                .capture_drops
+                .insert(interface_name.clone(), drops)
+                .unwrap_or(0);
+            if drops > previous_drops {
+                self.new_capture_drops
+                    .insert(interface_name, drops - previous_drops);
+            }
+        }
+        self.recent_capture_drops
+            .push_back(self.new_capture_drops.values().sum());
+        if self.recent_capture_drops.len() > RECALL_LENGTH {
+            self.recent_capture_drops.pop_front();
+        }
+    }
+    // the rates of the interfaces are counted over the same updates as those of the connections
+    fn update_interfaces(
+        &mut self,
+        interface_counters: HashMap<String, InterfaceCounters>,
+        paused: bool,
+    ) {
+        for (interface_name, counters) in &interface_counters {
+            self.first_interface_counters
+                .entry(interface_name.clone())
+                .or_insert_with(|| counters.clone());
+        }
+        self.cumulative_interfaces = count_interfaces(
+            &interface_counters,
+            &self.first_interface_counters,
+            &self.first_interface_counters,
+            1,
+        );
+        if paused {
+            return;
+        }
+        self.interface_counters.push_back(interface_counters);
+        if self.interface_counters.len() > RECALL_LENGTH + 1 {
+            self.interface_counters.pop_front();
+        }
+        let latest = self.interface_counters.back().unwrap();
+        let earliest = self.interface_counters.front().unwrap();
+        let ticks = (self.interface_counters.len() as u128 - 1).max(1);
+        self.interfaces = count_interfaces(latest, earliest, &self.first_interface_counters, ticks);
     }
 }
@@ -6,24 +6,31 @@ mod os;
 #[cfg(test)]
 mod tests;
 
-use display::{RawTerminalBackend, Ui};
+use display::{serve_prometheus_metrics, PrometheusMetrics, RawTerminalBackend, SortBy, Ui};
 use network::{
     dns::{self, IpTable},
-    Connection, LocalSocket, Sniffer, Utilization,
+    CaptureFile, CaptureFilter, Connection, LocalSocket, ProcessTreeFilter, Sniffer, SocketCache,
+    SocketCounters, Utilization,
 };
 use os::OnSigWinch;
 
 use ::pnet_bandwhich_fork::datalink::{DataLinkReceiver, NetworkInterface};
-use ::std::collections::HashMap;
+use ::std::collections::{HashMap, HashSet};
+use ::std::net::{IpAddr, SocketAddr, TcpListener};
+use ::std::path::PathBuf;
+use ::std::str::FromStr;
 use ::std::sync::atomic::{AtomicBool, Ordering};
+use ::std::sync::mpsc::sync_channel;
 use ::std::sync::{Arc, Mutex};
 use ::std::thread::park_timeout;
 use ::std::{thread, time};
 use ::termion::event::{Event, Key};
 use ::tui::backend::Backend;
 
-use std::process;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{self, Child, Stdio};
 
+use ::chrono::prelude::*;
 use ::std::io;
 use ::std::time::Instant;
 use ::termion::raw::IntoRawMode;
@@ -31,8 +38,11 @@ use ::tui::backend::TermionBackend;
 use structopt::StructOpt;
 
 const DISPLAY_DELTA: time::Duration = time::Duration::from_millis(1000);
+// the sockets are polled again for unknown owners at most this often, so that a stream of new
+// connections nobody owns (eg. forwarded traffic) does not keep the scanner busy
+const MIN_RESCAN_INTERVAL: time::Duration = time::Duration::from_millis(300);
 
-#[derive(StructOpt, Debug)]
+#[derive(StructOpt, Debug, Default)]
 #[structopt(name = "bandwhich")]
 pub struct Opt {
     #[structopt(short, long)]
@@ -41,14 +51,41 @@ pub struct Opt {
     #[structopt(short, long)]
     /// Machine friendlier output
     raw: bool,
+    #[structopt(long, possible_values = &["text", "json", "csv"])]
+    /// The format of the machine friendlier output, implies --raw
+    output_format: Option<OutputFormat>,
     #[structopt(short, long)]
     /// Do not attempt to resolve IPs to their hostnames
     no_resolve: bool,
+    #[structopt(long, parse(from_os_str), conflicts_with = "interface")]
+    /// Read packets from a pcap or pcapng capture file instead of listening on an interface
+    pcap_file: Option<PathBuf>,
+    #[structopt(long, conflicts_with_all = &["interface", "pcap-file"])]
+    /// Read a live pcap stream from stdin instead of listening on an interface, eg. from tcpdump -w -
+    pcap_stdin: bool,
+    #[structopt(long, number_of_values = 1)]
+    /// An IP address of the host the capture was taken on, used to tell uploads from downloads.
+    /// Can be repeated
+    local_ip: Vec<IpAddr>,
+    #[structopt(long, conflicts_with_all = &["pcap-file", "pcap-stdin"])]
+    /// Only capture the packets matching this tcpdump style filter, eg. "tcp port 443". The packets
+    /// are filtered in the kernel (Linux only). The ports of IPv6 packets with extension headers
+    /// are not matched
+    bpf: Option<String>,
+    #[structopt(long)]
+    /// Serve cumulative byte counters for Prometheus on http://<address>/metrics, eg. 127.0.0.1:9184
+    prometheus_listen: Option<SocketAddr>,
+    #[structopt(flatten)]
+    capture_filter: CaptureFilter,
     #[structopt(flatten)]
     render_opts: RenderOpts,
+    #[structopt(last = true, conflicts_with_all = &["pcap-file", "pcap-stdin"])]
+    /// A command to run, eg. bandwhich -- curl example.com. Only its traffic and that of the
+    /// processes it spawns is counted, and bandwhich exits with its exit code once it is done
+    command: Vec<String>,
 }
 
-#[derive(StructOpt, Debug)]
+#[derive(StructOpt, Debug, Default)]
 pub struct RenderOpts {
     #[structopt(short, long)]
     /// Show processes table only
@@ -59,49 +96,193 @@ pub struct RenderOpts {
     #[structopt(short, long)]
     /// Show remote addresses table only
     addresses: bool,
+    #[structopt(short, long)]
+    /// Show users table only
+    users: bool,
+    #[structopt(long)]
+    /// Show containers table only
+    containers: bool,
+    #[structopt(long)]
+    /// Show systemd units table only
+    units: bool,
+    #[structopt(long)]
+    /// Show interfaces table only, with the traffic counted by the kernel next to the sniffed one
+    interfaces: bool,
+    #[structopt(
+        long,
+        possible_values = &["bandwidth", "upload", "download", "total", "connections", "name"]
+    )]
+    /// How to sort the tables (and the raw output), can be changed at runtime with <s> or <b/u/d/+/c/n>
+    sort: Option<SortBy>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = &'static str;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err("Unknown output format"),
+        }
+    }
 }
 
 fn main() {
-    if let Err(err) = try_main() {
-        eprintln!("Error: {}", err);
-        process::exit(2);
+    match try_main() {
+        Ok(Some(exit_code)) => process::exit(exit_code),
+        Ok(None) => (),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            process::exit(2);
+        }
     }
 }
 
-fn try_main() -> Result<(), failure::Error> {
+// the exit code is that of the command run after --, if any
+fn try_main() -> Result<Option<i32>, failure::Error> {
     #[cfg(target_os = "windows")]
     compile_error!("Sorry, no implementations for Windows yet :( - PRs welcome!");
 
-    use os::get_input;
-    let opts = Opt::from_args();
-    let os_input = get_input(&opts.interface, !opts.no_resolve)?;
+    use os::{check_process_parents, get_input};
+    let mut opts = Opt::from_args();
+    opts.raw = opts.raw || opts.output_format.is_some();
+    if opts.capture_filter.with_children || !opts.command.is_empty() {
+        check_process_parents()?;
+    }
+    let mut os_input = get_input(
+        &opts.interface,
+        !opts.no_resolve,
+        &opts.pcap_file,
+        opts.pcap_stdin,
+        &opts.local_ip,
+        &opts.bpf,
+    )?;
+    if let Some(address) = opts.prometheus_listen {
+        match TcpListener::bind(address) {
+            Ok(listener) => os_input.prometheus_listener = Some(listener),
+            Err(e) => failure::bail!("Cannot serve the metrics on {}: {}", address, e),
+        }
+    }
     let raw_mode = opts.raw;
-    if raw_mode {
+    let command_summary = if raw_mode {
         let terminal_backend = RawTerminalBackend {};
-        start(terminal_backend, os_input, opts);
+        os_input.command = spawn_command(&opts.command, raw_mode)?;
+        start(terminal_backend, os_input, opts)
     } else {
         match io::stdout().into_raw_mode() {
             Ok(stdout) => {
                 let terminal_backend = TermionBackend::new(stdout);
-                start(terminal_backend, os_input, opts);
+                os_input.command = spawn_command(&opts.command, raw_mode)?;
+                start(terminal_backend, os_input, opts)
             }
             Err(_) => failure::bail!(
                 "Failed to get stdout: if you are trying to pipe 'bandwhich' you should use the --raw flag"
             ),
         }
-    }
-    Ok(())
+    };
+    Ok(command_summary.map(|command_summary| {
+        for line in command_summary.lines {
+            eprintln!("{}", line);
+        }
+        command_summary.exit_code
+    }))
+}
+
+// the command is only started once everything else is ready, so none of its traffic is missed
+fn spawn_command(command: &[String], raw_mode: bool) -> Result<Option<Child>, failure::Error> {
+    let (program, args) = match command.split_first() {
+        Some(program_and_args) => program_and_args,
+        None => return Ok(None),
+    };
+    // its output would garble the ui, in raw mode it is interleaved with that of bandwhich
+    let output = || {
+        if raw_mode {
+            Stdio::inherit()
+        } else {
+            Stdio::null()
+        }
+    };
+    let child = process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(output())
+        .stderr(output())
+        .spawn()
+        .map_err(|err| failure::format_err!("Could not run {}: {}", program, err))?;
+    Ok(Some(child))
+}
+
+// how the command run after -- ended, and the traffic it made
+pub struct CommandSummary {
+    pub exit_code: i32,
+    pub lines: Vec<String>,
+}
+
+// the process owning a socket: its name is the short command name, the cmdline and the exe are
+// empty where they could not be read, the user is the name of its owner (or its uid if it has none)
+// and the cgroup and systemd unit are empty where cgroups are not available. The ancestors are the
+// pids and names of its parent, grandparent and so on, where they are known
+#[derive(Clone, Debug, Default)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub ancestors: Vec<(u32, String)>,
+    pub cmdline: String,
+    pub exe: String,
+    pub user: String,
+    pub cgroup: String,
+    pub unit: String,
+    pub container: Option<Container>,
+}
+
+// a container as told by the cgroup of its processes, and the kubernetes pod it is part of if any
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Container {
+    pub id: String,
+    pub pod_uid: Option<String>,
+}
+
+// the counters the kernel keeps for a network interface since it came up, errors and drops in both
+// directions added up, and its link speed in Mbit/s where the driver knows it
+#[derive(Clone, Debug, Default)]
+pub struct InterfaceCounters {
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub packets_received: u64,
+    pub packets_sent: u64,
+    pub errors: u64,
+    pub drops: u64,
+    pub speed: Option<u64>,
 }
 
 pub struct OpenSockets {
-    sockets_to_procs: HashMap<LocalSocket, String>,
+    sockets_to_procs: HashMap<LocalSocket, ProcessInfo>,
     connections: Vec<Connection>,
+    // the processes running at the poll, only those with sockets where the process table is not read
+    pids: HashSet<u32>,
 }
 
+// the packets the kernel dropped on each interface since the capture started
+pub type GetCaptureDrops = dyn FnMut() -> HashMap<String, u64> + Send;
+
 pub struct OsInputOutput {
     pub network_interfaces: Vec<NetworkInterface>,
     pub network_frames: Vec<Box<dyn DataLinkReceiver>>,
+    pub capture_file: Option<CaptureFile>,
+    pub socket_counters: Option<SocketCounters>,
+    pub prometheus_listener: Option<TcpListener>,
+    pub command: Option<Child>,
     pub get_open_sockets: fn() -> OpenSockets,
+    pub get_interface_counters: fn() -> HashMap<String, InterfaceCounters>,
+    pub get_capture_drops: Box<GetCaptureDrops>,
     pub keyboard_events: Box<dyn Iterator<Item = Event> + Send>,
     pub dns_client: Option<dns::Client>,
     pub on_winch: Box<OnSigWinch>,
@@ -109,26 +290,73 @@ pub struct OsInputOutput {
     pub write_to_stdout: Box<dyn FnMut(String) + Send>,
 }
 
-pub fn start<B>(terminal_backend: B, os_input: OsInputOutput, opts: Opt)
+pub fn start<B>(terminal_backend: B, os_input: OsInputOutput, opts: Opt) -> Option<CommandSummary>
 where
     B: Backend + Send + 'static,
 {
     let running = Arc::new(AtomicBool::new(true));
     let paused = Arc::new(AtomicBool::new(false));
+    let input_closed = Arc::new(AtomicBool::new(false));
 
     let mut active_threads = vec![];
 
     let keyboard_events = os_input.keyboard_events;
     let get_open_sockets = os_input.get_open_sockets;
+    let get_interface_counters = os_input.get_interface_counters;
+    let mut get_capture_drops = os_input.get_capture_drops;
     let mut write_to_stdout = os_input.write_to_stdout;
     let mut dns_client = os_input.dns_client;
     let on_winch = os_input.on_winch;
-    let cleanup = os_input.cleanup;
+    // called by whichever of the stdin and display handlers ends the program
+    let cleanup = Arc::new(Mutex::new(os_input.cleanup));
+    let mut command = os_input.command;
+    let command_pid = command.as_ref().map(Child::id);
+    let command_status = Arc::new(Mutex::new(None));
 
     let raw_mode = opts.raw;
+    let output_format = opts.output_format.unwrap_or(OutputFormat::Text);
+    let capture_file_mode = os_input.capture_file.is_some();
+
+    // when reading a capture file, its timestamps rather than the wall clock decide when a tick ends
+    let (capture_ticks, capture_ticks_receiver) = if os_input.capture_file.is_some() {
+        let (sender, receiver) = sync_channel(0);
+        (Some(sender), Some(receiver))
+    } else {
+        (None, None)
+    };
 
     let network_utilization = Arc::new(Mutex::new(Utilization::new()));
-    let ui = Arc::new(Mutex::new(Ui::new(terminal_backend, opts.render_opts)));
+    let socket_cache = Arc::new(Mutex::new(SocketCache::default()));
+    // the sniffers ask for the sockets to be polled again when they see one without an owner
+    let (rescan_requests, rescan_receiver) = sync_channel(1);
+    let capture_filter = Arc::new(opts.capture_filter);
+    // the rescans add to the tree as well, as the sockets of a short lived process may only ever
+    // be seen by them
+    let process_tree_filter = Arc::new(Mutex::new(match command_pid {
+        Some(command_pid) => Some(ProcessTreeFilter::new(&[command_pid])),
+        None if capture_filter.with_children => Some(ProcessTreeFilter::new(&capture_filter.pids)),
+        None => None,
+    }));
+    let process_info_available = !capture_file_mode && !opts.pcap_stdin;
+    let prometheus_metrics = os_input.prometheus_listener.map(|listener| {
+        let metrics = Arc::new(Mutex::new(PrometheusMetrics::new(process_info_available)));
+        // like the stdin handler in raw mode, the server is not joined: it serves until the program exits
+        thread::Builder::new()
+            .name("prometheus_server".to_string())
+            .spawn({
+                let metrics = metrics.clone();
+                move || serve_prometheus_metrics(listener, metrics)
+            })
+            .unwrap();
+        metrics
+    });
+    let ui = Arc::new(Mutex::new(Ui::new(
+        terminal_backend,
+        opts.render_opts,
+        raw_mode,
+        os_input.socket_counters.is_some(),
+        process_info_available,
+    )));
 
     if !raw_mode {
         active_threads.push(
@@ -155,20 +383,69 @@ where
         .spawn({
             let running = running.clone();
             let paused = paused.clone();
+            let input_closed = input_closed.clone();
             let network_utilization = network_utilization.clone();
+            let capture_filter = capture_filter.clone();
+            let cleanup = cleanup.clone();
+            let command_status = command_status.clone();
+            let socket_cache = socket_cache.clone();
+            let process_tree_filter = process_tree_filter.clone();
+            let ui = ui.clone();
             move || {
                 while running.load(Ordering::Acquire) {
                     let render_start_time = Instant::now();
-                    let utilization = { network_utilization.lock().unwrap().clone_and_reset() };
+                    let paused = paused.load(Ordering::SeqCst);
+                    // checked before the tick is taken, so that the last tick has all the traffic
+                    // the command made
+                    let command_exit_status = match command.as_mut().map(Child::try_wait) {
+                        Some(Ok(status)) => status,
+                        _ => None,
+                    };
+                    let mut tick = match capture_ticks_receiver.as_ref() {
+                        None => Some((
+                            network_utilization.lock().unwrap().clone_and_reset(),
+                            Local::now().timestamp(),
+                        )),
+                        Some(_) if paused => None,
+                        Some(capture_ticks) => match capture_ticks.recv() {
+                            Ok(tick) => Some(tick),
+                            Err(_) if raw_mode => break,
+                            // the capture is over, keep displaying its last tick
+                            Err(_) => None,
+                        },
+                    };
                     let OpenSockets {
                         sockets_to_procs,
                         connections,
+                        pids,
                     } = get_open_sockets();
+                    let sockets_to_procs = socket_cache
+                        .lock()
+                        .unwrap()
+                        .update(sockets_to_procs, Instant::now());
+                    let interface_counters = get_interface_counters();
+                    let capture_drops = get_capture_drops();
+                    if let Some((utilization, _)) = tick.as_mut() {
+                        capture_filter.retain_processes(utilization, &sockets_to_procs);
+                        if let Some(process_tree_filter) =
+                            process_tree_filter.lock().unwrap().as_mut()
+                        {
+                            process_tree_filter.retain_processes(
+                                utilization,
+                                &sockets_to_procs,
+                                &pids,
+                            );
+                        }
+                    }
                     let mut ip_to_host = IpTable::new();
                     if let Some(dns_client) = dns_client.as_mut() {
                         ip_to_host = dns_client.cache();
+                        let captured_connections = tick
+                            .iter()
+                            .flat_map(|(utilization, _)| utilization.connections.keys());
                         let unresolved_ips = connections
                             .iter()
+                            .chain(captured_connections)
                             .filter(|conn| !ip_to_host.contains_key(&conn.remote_socket.ip))
                             .map(|conn| conn.remote_socket.ip)
                             .collect::<Vec<_>>();
@@ -177,21 +454,64 @@ where
                     }
                     {
                         let mut ui = ui.lock().unwrap();
-                        let paused = paused.load(Ordering::SeqCst);
-                        if !paused {
-                            ui.update_state(sockets_to_procs, utilization, ip_to_host);
+                        if let Some((utilization, timestamp)) = tick {
+                            // the counters and the totals keep counting while the display is paused
+                            if let Some(prometheus_metrics) = prometheus_metrics.as_ref() {
+                                prometheus_metrics
+                                    .lock()
+                                    .unwrap()
+                                    .update(&sockets_to_procs, &utilization);
+                            }
+                            ui.update_state(
+                                sockets_to_procs,
+                                utilization,
+                                interface_counters,
+                                capture_drops,
+                                timestamp,
+                                paused,
+                            );
+                            ui.update_ip_to_host(ip_to_host);
+                            if raw_mode {
+                                match output_format {
+                                    OutputFormat::Text => {
+                                        ui.output_text(&mut write_to_stdout, timestamp)
+                                    }
+                                    OutputFormat::Json => {
+                                        ui.output_json(&mut write_to_stdout, timestamp)
+                                    }
+                                    OutputFormat::Csv => {
+                                        ui.output_csv(&mut write_to_stdout, timestamp)
+                                    }
+                                }
+                            }
                         }
-                        if raw_mode {
-                            ui.output_text(&mut write_to_stdout);
-                        } else {
+                        if !raw_mode {
                             ui.draw(paused);
                         }
                     }
+                    if let Some(status) = command_exit_status {
+                        *command_status.lock().unwrap() = Some(status);
+                        running.store(false, Ordering::Release);
+                        (cleanup.lock().unwrap())();
+                        break;
+                    }
+                    if raw_mode && input_closed.load(Ordering::Acquire) {
+                        break;
+                    }
+                    // a capture file is replayed in raw mode as fast as it can be read
                     let render_duration = render_start_time.elapsed();
-                    if render_duration < DISPLAY_DELTA {
+                    if render_duration < DISPLAY_DELTA && !(raw_mode && capture_file_mode) {
                         park_timeout(DISPLAY_DELTA - render_duration);
                     }
                 }
+                // a command still running when quitting is stopped along with bandwhich
+                if let Some(command) = command.as_mut() {
+                    let mut command_status = command_status.lock().unwrap();
+                    if command_status.is_none() {
+                        let _ = command.kill();
+                        *command_status = command.wait().ok();
+                    }
+                }
                 if !raw_mode {
                     let mut ui = ui.lock().unwrap();
                     ui.end();
@@ -200,33 +520,152 @@ where
         })
         .unwrap();
 
+    let stdin_handler = thread::Builder::new()
+        .name("stdin_handler".to_string())
+        .spawn({
+            let running = running.clone();
+            let display_handler = display_handler.thread().clone();
+            // the ui (and with it the terminal) is let go of once the display handler is done,
+            // even if this handler is still waiting for a key
+            let ui = Arc::downgrade(&ui);
+            move || {
+                for evt in keyboard_events {
+                    let ui = match ui.upgrade() {
+                        Some(ui) => ui,
+                        None => break,
+                    };
+                    // a filter being typed in gets every key but Ctrl-c
+                    let prompting = ui.lock().unwrap().is_prompting();
+                    match evt {
+                        Event::Key(Key::Ctrl('c')) | Event::Key(Key::Char('q'))
+                            if !prompting || evt == Event::Key(Key::Ctrl('c')) =>
+                        {
+                            running.store(false, Ordering::Release);
+                            (cleanup.lock().unwrap())();
+                            display_handler.unpark();
+                            break;
+                        }
+                        Event::Key(Key::Char(' ')) if !prompting => {
+                            paused.fetch_xor(true, Ordering::SeqCst);
+                            display_handler.unpark();
+                        }
+                        Event::Key(key) => {
+                            // redrawn right away rather than by unparking the display handler,
+                            // which would cut the current tick short
+                            let mut ui = ui.lock().unwrap();
+                            if ui.handle_key(key) && !raw_mode {
+                                ui.draw(paused.load(Ordering::SeqCst));
+                            }
+                        }
+                        _ => (),
+                    };
+                }
+            }
+        })
+        .unwrap();
+    // in raw mode the program ends when its input does, and with a command when the command does,
+    // regardless of stdin
+    if !raw_mode && command_pid.is_none() {
+        active_threads.push(stdin_handler);
+    }
+    let display_thread = display_handler.thread().clone();
+    active_threads.push(display_handler);
+
+    if let (Some(mut capture_file), Some(capture_ticks)) = (os_input.capture_file, capture_ticks) {
+        let running = running.clone();
+        let network_utilization = network_utilization.clone();
+        let capture_filter = capture_filter.clone();
+        active_threads.push(
+            thread::Builder::new()
+                .name("capture_file_handler".to_string())
+                .spawn(move || {
+                    let mut tick_end = None;
+                    while running.load(Ordering::Acquire) {
+                        let record = match capture_file.next_record() {
+                            Ok(Some(record)) => record,
+                            _ => break,
+                        };
+                        let tick_end = tick_end.get_or_insert(record.timestamp + DISPLAY_DELTA);
+                        while record.timestamp >= *tick_end {
+                            let utilization = network_utilization.lock().unwrap().clone_and_reset();
+                            if capture_ticks
+                                .send((utilization, tick_end.as_secs() as i64))
+                                .is_err()
+                            {
+                                return;
+                            }
+                            *tick_end += DISPLAY_DELTA;
+                        }
+                        if let Some(segment) =
+                            Sniffer::parse_frame(record.data, record.network_interface)
+                        {
+                            if capture_filter.matches_segment(&segment) {
+                                network_utilization.lock().unwrap().update(segment);
+                            }
+                        }
+                    }
+                    if let Some(tick_end) = tick_end {
+                        let utilization = network_utilization.lock().unwrap().clone_and_reset();
+                        let _ = capture_ticks.send((utilization, tick_end.as_secs() as i64));
+                    }
+                })
+                .unwrap(),
+        );
+    }
+
+    if let Some(mut socket_counters) = os_input.socket_counters {
+        let running = running.clone();
+        let network_utilization = network_utilization.clone();
+        let capture_filter = capture_filter.clone();
+        active_threads.push(
+            thread::Builder::new()
+                .name("socket_counters_handler".to_string())
+                .spawn(move || {
+                    while running.load(Ordering::Acquire) {
+                        for segment in socket_counters.poll() {
+                            if capture_filter.matches_segment(&segment) {
+                                network_utilization.lock().unwrap().update(segment);
+                            }
+                        }
+                        park_timeout(DISPLAY_DELTA);
+                    }
+                })
+                .unwrap(),
+        );
+    }
+
     active_threads.push(
         thread::Builder::new()
-            .name("stdin_handler".to_string())
+            .name("socket_scanner".to_string())
             .spawn({
-                let running = running.clone();
-                let display_handler = display_handler.thread().clone();
+                let socket_cache = socket_cache.clone();
+                // requests made during a poll or right after it are all answered by the next one,
+                // and the scanner is done once the sniffers are
                 move || {
-                    for evt in keyboard_events {
-                        match evt {
-                            Event::Key(Key::Ctrl('c')) | Event::Key(Key::Char('q')) => {
-                                running.store(false, Ordering::Release);
-                                cleanup();
-                                display_handler.unpark();
-                                break;
-                            }
-                            Event::Key(Key::Char(' ')) => {
-                                paused.fetch_xor(true, Ordering::SeqCst);
-                                display_handler.unpark();
-                            }
-                            _ => (),
-                        };
+                    for () in rescan_receiver {
+                        let scan_start_time = Instant::now();
+                        let OpenSockets {
+                            sockets_to_procs, ..
+                        } = get_open_sockets();
+                        if let Some(process_tree_filter) =
+                            process_tree_filter.lock().unwrap().as_mut()
+                        {
+                            process_tree_filter.add_descendants(&sockets_to_procs);
+                        }
+                        socket_cache
+                            .lock()
+                            .unwrap()
+                            .update(sockets_to_procs, Instant::now());
+                        if let Some(time_to_wait) =
+                            MIN_RESCAN_INTERVAL.checked_sub(scan_start_time.elapsed())
+                        {
+                            thread::sleep(time_to_wait);
+                        }
                     }
                 }
             })
             .unwrap(),
     );
-    active_threads.push(display_handler);
 
     let sniffer_threads = os_input
         .network_interfaces
@@ -235,7 +674,12 @@ where
         .map(|(iface, frames)| {
             let name = format!("sniffing_handler_{}", iface.name);
             let running = running.clone();
+            let input_closed = input_closed.clone();
+            let display_thread = display_thread.clone();
             let network_utilization = network_utilization.clone();
+            let capture_filter = capture_filter.clone();
+            let socket_cache = socket_cache.clone();
+            let rescan_requests = rescan_requests.clone();
 
             thread::Builder::new()
                 .name(name)
@@ -244,16 +688,62 @@ where
 
                     while running.load(Ordering::Acquire) {
                         if let Some(segment) = sniffer.next() {
-                            network_utilization.lock().unwrap().update(segment);
+                            if capture_filter.matches_segment(&segment) {
+                                let local_socket = segment.connection.local_socket;
+                                // the owner is only looked for once a tick for each connection
+                                let is_new = network_utilization.lock().unwrap().update(segment);
+                                if is_new
+                                    && socket_cache
+                                        .lock()
+                                        .unwrap()
+                                        .should_rescan(&local_socket, Instant::now())
+                                {
+                                    let _ = rescan_requests.try_send(());
+                                }
+                            }
+                        } else if sniffer.is_closed() {
+                            input_closed.store(true, Ordering::Release);
+                            display_thread.unpark();
+                            break;
                         }
                     }
                 })
                 .unwrap()
         })
         .collect::<Vec<_>>();
+    drop(rescan_requests);
     active_threads.extend(sniffer_threads);
 
     for thread_handler in active_threads {
         thread_handler.join().unwrap()
     }
+
+    let command_pid = command_pid?;
+    let mut lines = vec![match *command_status.lock().unwrap() {
+        Some(status) => match (status.code(), status.signal()) {
+            (Some(code), _) => format!("{} exited with code {}", opts.command.join(" "), code),
+            (None, signal) => format!(
+                "{} was killed by signal {}",
+                opts.command.join(" "),
+                signal.unwrap_or_default()
+            ),
+        },
+        None => format!(
+            "{} is still running as pid {}",
+            opts.command.join(" "),
+            command_pid
+        ),
+    }];
+    lines.extend(ui.lock().unwrap().command_summary());
+    // like a shell, a command killed by a signal exits with 128 and the signal
+    let exit_code = command_status
+        .lock()
+        .unwrap()
+        .map(|status| {
+            status
+                .code()
+                .unwrap_or_else(|| 128 + status.signal().unwrap_or_default())
+        })
+        .unwrap_or(0);
+    Some(CommandSummary { exit_code, lines })
 }
@@ -0,0 +1,505 @@
+use ::std::net::IpAddr;
+
+use ::ipnetwork::IpNetwork;
+
+// a subset of the tcpdump filter syntax, compiled to classic BPF for ethernet or raw ip frames:
+//   [tcp|udp|icmp|icmp6] [src|dst] (host IP | net CIDR | port PORT), tcp, udp, icmp, icmp6, ip, ip6
+// combined with and (&&), or (||), not (!) and parentheses. The ports of ipv6 packets are only
+// found when no extension headers come before the tcp or udp header
+
+const ETHERNET_HEADER_LENGTH: u32 = 14;
+const ETHERTYPE_OFFSET: u32 = 12;
+const ETHERTYPE_IPV4: u32 = 0x0800;
+const ETHERTYPE_IPV6: u32 = 0x86dd;
+// the version is the high nibble of the first byte of an ip header
+const IP_VERSION_MASK: u32 = 0xf0;
+const IP_VERSION_4: u32 = 0x40;
+const IP_VERSION_6: u32 = 0x60;
+const IPV6_HEADER_LENGTH: u32 = 40;
+// the whole frame is passed on when it matches
+const ACCEPT_LENGTH: u32 = 262_144;
+
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_LD_H_ABS: u16 = 0x28;
+const BPF_LD_B_ABS: u16 = 0x30;
+const BPF_LD_H_IND: u16 = 0x48;
+const BPF_LDX_B_MSH: u16 = 0xb1;
+const BPF_ALU_AND_K: u16 = 0x54;
+const BPF_JMP_JEQ_K: u16 = 0x15;
+const BPF_JMP_JSET_K: u16 = 0x45;
+const BPF_RET_K: u16 = 0x06;
+
+// what the frames of an interface start with: an ethernet header, or the ip header itself as on
+// tun, wireguard and ppp interfaces
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkType {
+    Ethernet,
+    RawIp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum IpVersion {
+    V4,
+    V6,
+}
+
+// laid out like the kernel's struct sock_filter
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BpfInstruction {
+    pub code: u16,
+    pub jt: u8,
+    pub jf: u8,
+    pub k: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Transport {
+    Tcp,
+    Udp,
+    Icmp,
+    Icmp6,
+}
+
+impl Transport {
+    fn number(self) -> u32 {
+        match self {
+            Transport::Tcp => 6,
+            Transport::Udp => 17,
+            Transport::Icmp => 1,
+            Transport::Icmp6 => 58,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Side {
+    Source,
+    Destination,
+    Either,
+}
+
+#[derive(Debug)]
+enum Expression {
+    Ipv4,
+    Ipv6,
+    Transport(Transport),
+    Net(Side, IpNetwork),
+    Port(Side, u16),
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+}
+
+struct Parser<'a> {
+    tokens: Vec<&'a str>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(expression: &'a str) -> Self {
+        let tokens = expression
+            .split_whitespace()
+            .flat_map(|word| {
+                // parentheses do not need to be surrounded by spaces
+                let mut tokens = Vec::new();
+                let mut rest = word;
+                while let Some(index) = rest.find(|c| c == '(' || c == ')') {
+                    if index > 0 {
+                        tokens.push(&rest[..index]);
+                    }
+                    tokens.push(&rest[index..=index]);
+                    rest = &rest[index + 1..];
+                }
+                if !rest.is_empty() {
+                    tokens.push(rest);
+                }
+                tokens
+            })
+            .collect();
+        Parser {
+            tokens,
+            position: 0,
+        }
+    }
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).copied()
+    }
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+    fn next_value(&mut self, keyword: &str) -> Result<&'a str, failure::Error> {
+        match self.next() {
+            Some(value) => Ok(value),
+            None => failure::bail!("Missing a value after \"{}\"", keyword),
+        }
+    }
+    fn parse(mut self) -> Result<Expression, failure::Error> {
+        let expression = self.parse_or()?;
+        if let Some(token) = self.peek() {
+            failure::bail!("Unexpected \"{}\"", token);
+        }
+        Ok(expression)
+    }
+    fn parse_or(&mut self) -> Result<Expression, failure::Error> {
+        let mut expression = self.parse_and()?;
+        while let Some("or") | Some("||") = self.peek() {
+            self.next();
+            expression = Expression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+        Ok(expression)
+    }
+    fn parse_and(&mut self) -> Result<Expression, failure::Error> {
+        let mut expression = self.parse_unary()?;
+        while let Some("and") | Some("&&") = self.peek() {
+            self.next();
+            expression = Expression::And(Box::new(expression), Box::new(self.parse_unary()?));
+        }
+        Ok(expression)
+    }
+    fn parse_unary(&mut self) -> Result<Expression, failure::Error> {
+        match self.peek() {
+            Some("not") | Some("!") => {
+                self.next();
+                Ok(Expression::Not(Box::new(self.parse_unary()?)))
+            }
+            Some("(") => {
+                self.next();
+                let expression = self.parse_or()?;
+                match self.next() {
+                    Some(")") => Ok(expression),
+                    _ => failure::bail!("Missing a closing parenthesis"),
+                }
+            }
+            _ => self.parse_primitive(),
+        }
+    }
+    fn parse_primitive(&mut self) -> Result<Expression, failure::Error> {
+        let transport = match self.peek() {
+            Some("tcp") => Some(Transport::Tcp),
+            Some("udp") => Some(Transport::Udp),
+            Some("icmp") => Some(Transport::Icmp),
+            Some("icmp6") => Some(Transport::Icmp6),
+            _ => None,
+        };
+        if transport.is_some() {
+            self.next();
+        }
+        let side = match self.peek() {
+            Some("src") => Some(Side::Source),
+            Some("dst") => Some(Side::Destination),
+            _ => None,
+        };
+        if side.is_some() {
+            self.next();
+        }
+        let primitive = match (transport, side, self.peek()) {
+            (_, _, Some("host")) => {
+                self.next();
+                let value = self.next_value("host")?;
+                match value.parse::<IpAddr>() {
+                    Ok(ip) => Expression::Net(side.unwrap_or(Side::Either), IpNetwork::from(ip)),
+                    Err(_) => failure::bail!("\"{}\" is not an IP address", value),
+                }
+            }
+            (_, _, Some("net")) => {
+                self.next();
+                let value = self.next_value("net")?;
+                match value.parse::<IpNetwork>() {
+                    Ok(network) => Expression::Net(side.unwrap_or(Side::Either), network),
+                    Err(_) => failure::bail!("\"{}\" is not a network, eg. 10.0.0.0/8", value),
+                }
+            }
+            (_, _, Some("port")) => {
+                self.next();
+                let value = self.next_value("port")?;
+                match value.parse::<u16>() {
+                    Ok(port) => Expression::Port(side.unwrap_or(Side::Either), port),
+                    Err(_) => failure::bail!("\"{}\" is not a port", value),
+                }
+            }
+            (Some(transport), None, _) => return Ok(Expression::Transport(transport)),
+            (None, None, Some("ip")) => {
+                self.next();
+                return Ok(Expression::Ipv4);
+            }
+            (None, None, Some("ip6")) => {
+                self.next();
+                return Ok(Expression::Ipv6);
+            }
+            (_, _, Some(token)) => failure::bail!("Unexpected \"{}\"", token),
+            (_, _, None) => failure::bail!("The filter expression ended unexpectedly"),
+        };
+        Ok(match transport {
+            Some(transport) => Expression::And(
+                Box::new(Expression::Transport(transport)),
+                Box::new(primitive),
+            ),
+            None => primitive,
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Target {
+    Next,
+    Label(usize),
+}
+
+enum Operation {
+    Instruction {
+        code: u16,
+        k: u32,
+        jt: Target,
+        jf: Target,
+    },
+    Label(usize),
+}
+
+// jumps are made to labels, which are only ever placed after the jumps to them as classic BPF
+// can only jump forward
+struct Compiler {
+    operations: Vec<Operation>,
+    label_count: usize,
+    link_type: LinkType,
+}
+
+impl Compiler {
+    fn new(link_type: LinkType) -> Self {
+        Compiler {
+            operations: Vec::new(),
+            label_count: 0,
+            link_type,
+        }
+    }
+    fn ip_offset(&self) -> u32 {
+        match self.link_type {
+            LinkType::Ethernet => ETHERNET_HEADER_LENGTH,
+            LinkType::RawIp => 0,
+        }
+    }
+    // what ip_version leaves loaded for a packet of the given version
+    fn loaded_version(&self, version: IpVersion) -> u32 {
+        match (self.link_type, version) {
+            (LinkType::Ethernet, IpVersion::V4) => ETHERTYPE_IPV4,
+            (LinkType::Ethernet, IpVersion::V6) => ETHERTYPE_IPV6,
+            (LinkType::RawIp, IpVersion::V4) => IP_VERSION_4,
+            (LinkType::RawIp, IpVersion::V6) => IP_VERSION_6,
+        }
+    }
+    fn new_label(&mut self) -> Target {
+        self.label_count += 1;
+        Target::Label(self.label_count - 1)
+    }
+    fn place(&mut self, label: Target) {
+        if let Target::Label(label) = label {
+            self.operations.push(Operation::Label(label));
+        }
+    }
+    fn load(&mut self, code: u16, k: u32) {
+        self.jump(code, k, Target::Next, Target::Next);
+    }
+    fn jump(&mut self, code: u16, k: u32, jt: Target, jf: Target) {
+        self.operations
+            .push(Operation::Instruction { code, k, jt, jf });
+    }
+    // leaves the ethertype or the ip version loaded for a following ipv6 check
+    fn ip_version(&mut self, version: IpVersion, jt: Target, jf: Target) {
+        match self.link_type {
+            LinkType::Ethernet => self.load(BPF_LD_H_ABS, ETHERTYPE_OFFSET),
+            LinkType::RawIp => {
+                self.load(BPF_LD_B_ABS, 0);
+                self.load(BPF_ALU_AND_K, IP_VERSION_MASK);
+            }
+        }
+        let loaded_version = self.loaded_version(version);
+        self.jump(BPF_JMP_JEQ_K, loaded_version, jt, jf);
+    }
+    fn transport_number(&mut self, offset: u32, transport: Transport, jt: Target, jf: Target) {
+        self.load(BPF_LD_B_ABS, offset);
+        self.jump(BPF_JMP_JEQ_K, transport.number(), jt, jf);
+    }
+    fn compile(&mut self, expression: &Expression, jt: Target, jf: Target) {
+        let ip_offset = self.ip_offset();
+        let ipv6_version = self.loaded_version(IpVersion::V6);
+        match expression {
+            Expression::Ipv4 => self.ip_version(IpVersion::V4, jt, jf),
+            Expression::Ipv6 => self.ip_version(IpVersion::V6, jt, jf),
+            Expression::Transport(Transport::Icmp) => {
+                self.ip_version(IpVersion::V4, Target::Next, jf);
+                self.transport_number(ip_offset + 9, Transport::Icmp, jt, jf);
+            }
+            Expression::Transport(Transport::Icmp6) => {
+                self.ip_version(IpVersion::V6, Target::Next, jf);
+                self.transport_number(ip_offset + 6, Transport::Icmp6, jt, jf);
+            }
+            Expression::Transport(transport) => {
+                let ipv6 = self.new_label();
+                self.ip_version(IpVersion::V4, Target::Next, ipv6);
+                self.transport_number(ip_offset + 9, *transport, jt, jf);
+                self.place(ipv6);
+                self.jump(BPF_JMP_JEQ_K, ipv6_version, Target::Next, jf);
+                self.transport_number(ip_offset + 6, *transport, jt, jf);
+            }
+            Expression::Net(_, network) if network.prefix() == 0 => match network {
+                IpNetwork::V4(_) => self.compile(&Expression::Ipv4, jt, jf),
+                IpNetwork::V6(_) => self.compile(&Expression::Ipv6, jt, jf),
+            },
+            Expression::Net(side, network) => {
+                // the addresses are compared a 32 bit word at a time
+                let (version, source_offset, destination_offset, words) = match network {
+                    IpNetwork::V4(network) => (
+                        IpVersion::V4,
+                        ip_offset + 12,
+                        ip_offset + 16,
+                        vec![(u32::from(network.network()), u32::from(network.mask()))],
+                    ),
+                    IpNetwork::V6(network) => {
+                        let address = u128::from(network.network());
+                        let mask = u128::from(network.mask());
+                        let words = (0..4)
+                            .map(|word| {
+                                let shift = 96 - word * 32;
+                                ((address >> shift) as u32, (mask >> shift) as u32)
+                            })
+                            .filter(|(_, mask)| *mask != 0)
+                            .collect();
+                        (IpVersion::V6, ip_offset + 8, ip_offset + 24, words)
+                    }
+                };
+                let offsets = match side {
+                    Side::Source => vec![source_offset],
+                    Side::Destination => vec![destination_offset],
+                    Side::Either => vec![source_offset, destination_offset],
+                };
+                self.ip_version(version, Target::Next, jf);
+                for (index, offset) in offsets.iter().enumerate() {
+                    let last_offset = index == offsets.len() - 1;
+                    let other_offset = self.new_label();
+                    let no_match = if last_offset { jf } else { other_offset };
+                    for (word, (address, mask)) in words.iter().enumerate() {
+                        let last_word = word == words.len() - 1;
+                        self.load(BPF_LD_W_ABS, offset + word as u32 * 4);
+                        if *mask != u32::max_value() {
+                            self.load(BPF_ALU_AND_K, *mask);
+                        }
+                        let matched = if last_word { jt } else { Target::Next };
+                        self.jump(BPF_JMP_JEQ_K, *address, matched, no_match);
+                    }
+                    self.place(other_offset);
+                }
+            }
+            Expression::Port(side, port) => {
+                let port_offsets = match side {
+                    Side::Source => vec![0],
+                    Side::Destination => vec![2],
+                    Side::Either => vec![0, 2],
+                };
+                let compare_ports = |compiler: &mut Compiler, load: u16, header_length: u32| {
+                    for (index, offset) in port_offsets.iter().enumerate() {
+                        let no_match = if index == port_offsets.len() - 1 {
+                            jf
+                        } else {
+                            Target::Next
+                        };
+                        compiler.load(load, header_length + offset);
+                        compiler.jump(BPF_JMP_JEQ_K, u32::from(*port), jt, no_match);
+                    }
+                };
+                let ipv6 = self.new_label();
+                self.ip_version(IpVersion::V4, Target::Next, ipv6);
+                let ipv4_ports = self.new_label();
+                self.load(BPF_LD_B_ABS, ip_offset + 9);
+                self.jump(
+                    BPF_JMP_JEQ_K,
+                    Transport::Tcp.number(),
+                    ipv4_ports,
+                    Target::Next,
+                );
+                self.jump(BPF_JMP_JEQ_K, Transport::Udp.number(), Target::Next, jf);
+                self.place(ipv4_ports);
+                // only the first fragment of a packet has its ports
+                self.load(BPF_LD_H_ABS, ip_offset + 6);
+                self.jump(BPF_JMP_JSET_K, 0x1fff, jf, Target::Next);
+                self.load(BPF_LDX_B_MSH, ip_offset);
+                compare_ports(self, BPF_LD_H_IND, ip_offset);
+                self.place(ipv6);
+                self.jump(BPF_JMP_JEQ_K, ipv6_version, Target::Next, jf);
+                let ipv6_ports = self.new_label();
+                self.load(BPF_LD_B_ABS, ip_offset + 6);
+                self.jump(
+                    BPF_JMP_JEQ_K,
+                    Transport::Tcp.number(),
+                    ipv6_ports,
+                    Target::Next,
+                );
+                self.jump(BPF_JMP_JEQ_K, Transport::Udp.number(), Target::Next, jf);
+                self.place(ipv6_ports);
+                compare_ports(self, BPF_LD_H_ABS, ip_offset + IPV6_HEADER_LENGTH);
+            }
+            Expression::Not(expression) => self.compile(expression, jf, jt),
+            Expression::And(left, right) => {
+                let right_label = self.new_label();
+                self.compile(left, right_label, jf);
+                self.place(right_label);
+                self.compile(right, jt, jf);
+            }
+            Expression::Or(left, right) => {
+                let right_label = self.new_label();
+                self.compile(left, jt, right_label);
+                self.place(right_label);
+                self.compile(right, jt, jf);
+            }
+        }
+    }
+    fn assemble(self) -> Result<Vec<BpfInstruction>, failure::Error> {
+        let mut label_positions = vec![0; self.label_count];
+        let mut position = 0;
+        for operation in &self.operations {
+            match operation {
+                Operation::Label(label) => label_positions[*label] = position,
+                Operation::Instruction { .. } => position += 1,
+            }
+        }
+        let mut instructions = Vec::new();
+        for operation in self.operations {
+            if let Operation::Instruction { code, k, jt, jf } = operation {
+                let position = instructions.len();
+                let offset = |target: Target| match target {
+                    Target::Next => Some(0),
+                    Target::Label(label) => {
+                        let distance = label_positions[label] - position - 1;
+                        if distance > u8::max_value() as usize {
+                            None
+                        } else {
+                            Some(distance as u8)
+                        }
+                    }
+                };
+                match (offset(jt), offset(jf)) {
+                    (Some(jt), Some(jf)) => instructions.push(BpfInstruction { code, jt, jf, k }),
+                    _ => failure::bail!("The filter expression is too long"),
+                }
+            }
+        }
+        Ok(instructions)
+    }
+}
+
+pub fn compile_bpf(
+    expression: &str,
+    link_type: LinkType,
+) -> Result<Vec<BpfInstruction>, failure::Error> {
+    let expression = Parser::new(expression).parse()?;
+    let mut compiler = Compiler::new(link_type);
+    let accept = compiler.new_label();
+    let drop = compiler.new_label();
+    compiler.compile(&expression, accept, drop);
+    compiler.place(accept);
+    compiler.load(BPF_RET_K, ACCEPT_LENGTH);
+    compiler.place(drop);
+    compiler.load(BPF_RET_K, 0);
+    compiler.assemble()
+}
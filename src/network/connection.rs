@@ -1,10 +1,15 @@
 use ::std::collections::HashMap;
 use ::std::fmt;
-use ::std::net::IpAddr;
+use ::std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 use ::std::net::SocketAddr;
 
-#[derive(PartialEq, Hash, Eq, Clone, PartialOrd, Ord, Debug, Copy)]
+use ::serde::Serialize;
+
+use crate::ProcessInfo;
+
+#[derive(PartialEq, Hash, Eq, Clone, PartialOrd, Ord, Debug, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Protocol {
     Tcp,
     Udp,
@@ -74,6 +79,28 @@ pub fn display_connection_string(
     )
 }
 
+// the process of a socket, or of the socket listening on all the addresses for its port
+pub fn get_socket_owner<'a>(
+    sockets_to_procs: &'a HashMap<LocalSocket, ProcessInfo>,
+    local_socket: &LocalSocket,
+) -> Option<&'a ProcessInfo> {
+    if let Some(process_info) = sockets_to_procs.get(local_socket) {
+        Some(process_info)
+    } else if let Some(process_info) = sockets_to_procs.get(&LocalSocket {
+        ip: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+        port: local_socket.port,
+        protocol: local_socket.protocol,
+    }) {
+        Some(process_info)
+    } else {
+        sockets_to_procs.get(&LocalSocket {
+            ip: IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0)),
+            port: local_socket.port,
+            protocol: local_socket.protocol,
+        })
+    }
+}
+
 impl Connection {
     pub fn new(
         remote_socket: SocketAddr,
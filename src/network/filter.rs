@@ -0,0 +1,281 @@
+use ::std::collections::{HashMap, HashSet};
+use ::std::net::{IpAddr, ToSocketAddrs};
+use ::std::str::FromStr;
+
+use ::ipnetwork::IpNetwork;
+use ::structopt::StructOpt;
+
+use crate::network::{get_socket_owner, LocalSocket, Segment, Utilization};
+use crate::ProcessInfo;
+
+// a filter value, or with a leading ! everything but it
+#[derive(Debug, Clone)]
+pub struct Negatable<T> {
+    pub negated: bool,
+    pub value: T,
+}
+
+impl<T: FromStr> FromStr for Negatable<T> {
+    type Err = T::Err;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (negated, value) = match value.strip_prefix('!') {
+            Some(value) => (true, value),
+            None => (false, value),
+        };
+        Ok(Negatable {
+            negated,
+            value: value.parse()?,
+        })
+    }
+}
+
+// a host is resolved once, when the arguments are parsed
+#[derive(Debug, Clone)]
+pub struct Host {
+    pub addresses: Vec<IpAddr>,
+}
+
+impl FromStr for Host {
+    type Err = &'static str;
+    fn from_str(host: &str) -> Result<Self, Self::Err> {
+        let addresses = (host, 0)
+            .to_socket_addrs()
+            .map_err(|_| "Could not resolve host")?
+            .map(|socket_address| socket_address.ip())
+            .collect::<Vec<_>>();
+        if addresses.is_empty() {
+            return Err("Could not resolve host");
+        }
+        Ok(Host { addresses })
+    }
+}
+
+// values of the same option are alternatives, while different options must all match
+fn matches<T>(filters: &[Negatable<T>], matches_value: impl Fn(&T) -> bool) -> bool {
+    let mut has_wanted_values = false;
+    let mut wanted_value_matched = false;
+    for filter in filters {
+        let value_matched = matches_value(&filter.value);
+        if filter.negated && value_matched {
+            return false;
+        } else if !filter.negated {
+            has_wanted_values = true;
+            wanted_value_matched |= value_matched;
+        }
+    }
+    !has_wanted_values || wanted_value_matched
+}
+
+#[derive(StructOpt, Debug, Default)]
+pub struct CaptureFilter {
+    #[structopt(
+        name = "filter-process", long = "filter-process",
+        number_of_values = 1,
+        conflicts_with_all = &["pcap-file", "pcap-stdin"]
+    )]
+    /// Only count the traffic of this process, or with a leading ! all but its traffic. Can be repeated
+    pub processes: Vec<Negatable<String>>,
+    #[structopt(
+        name = "pid", long = "pid",
+        number_of_values = 1,
+        conflicts_with_all = &["pcap-file", "pcap-stdin", "command"]
+    )]
+    /// Only count the traffic of the process with this pid. Can be repeated
+    pub pids: Vec<u32>,
+    #[structopt(long, requires = "pid")]
+    /// Count the traffic of the processes spawned by those given with --pid as well
+    pub with_children: bool,
+    #[structopt(name = "filter-port", long = "filter-port", number_of_values = 1)]
+    /// Only count traffic from or to this (local or remote) port, or with a leading ! all but it.
+    /// Can be repeated
+    pub ports: Vec<Negatable<u16>>,
+    #[structopt(name = "filter-host", long = "filter-host", number_of_values = 1)]
+    /// Only count traffic with this remote host or IP, or with a leading ! all but it. Can be repeated
+    pub hosts: Vec<Negatable<Host>>,
+    #[structopt(name = "filter-net", long = "filter-net", number_of_values = 1)]
+    /// Only count traffic with remote IPs in this network, eg. 10.0.0.0/8, or with a leading ! all
+    /// but them. Can be repeated
+    pub networks: Vec<Negatable<IpNetwork>>,
+}
+
+impl CaptureFilter {
+    pub fn matches_segment(&self, segment: &Segment) -> bool {
+        let connection = &segment.connection;
+        let remote_ip = connection.remote_socket.ip;
+        matches(&self.ports, |port| {
+            *port == connection.local_socket.port || *port == connection.remote_socket.port
+        }) && matches(&self.hosts, |host| host.addresses.contains(&remote_ip))
+            && matches(&self.networks, |network| network.contains(remote_ip))
+    }
+    // segments do not know their process, so the process and pid filters are applied to each tick
+    // once its sockets are known (the children of pids are followed by a ProcessTreeFilter)
+    pub fn retain_processes(
+        &self,
+        network_utilization: &mut Utilization,
+        sockets_to_procs: &HashMap<LocalSocket, ProcessInfo>,
+    ) {
+        let filter_pids = !self.pids.is_empty() && !self.with_children;
+        if self.processes.is_empty() && !filter_pids {
+            return;
+        }
+        network_utilization.connections.retain(|connection, _| {
+            let process_info = get_socket_owner(sockets_to_procs, &connection.local_socket);
+            let process_name = process_info
+                .map(|process_info| process_info.name.as_str())
+                .unwrap_or("<UNKNOWN>");
+            matches(&self.processes, |wanted_process_name| {
+                wanted_process_name == process_name
+            }) && (!filter_pids
+                || process_info.map_or(false, |process_info| self.pids.contains(&process_info.pid)))
+        });
+    }
+}
+
+// the traffic of some processes (a command, or those given with --pid --with-children) and of
+// the processes they spawn, which stay part of their tree once they are known even if their parent
+// is gone by the next tick
+pub struct ProcessTreeFilter {
+    roots: HashSet<u32>,
+    pids: HashSet<u32>,
+}
+
+impl ProcessTreeFilter {
+    pub fn new(root_pids: &[u32]) -> Self {
+        ProcessTreeFilter {
+            roots: root_pids.iter().copied().collect(),
+            pids: root_pids.iter().copied().collect(),
+        }
+    }
+    // the processes of the sockets that were spawned by a process of the tree join it
+    pub fn add_descendants(&mut self, sockets_to_procs: &HashMap<LocalSocket, ProcessInfo>) {
+        for process_info in sockets_to_procs.values() {
+            // the ancestors below the closest known one are part of the tree as well
+            if let Some(known_ancestor) = process_info
+                .ancestors
+                .iter()
+                .position(|(pid, _)| self.pids.contains(pid))
+            {
+                self.pids.insert(process_info.pid);
+                self.pids.extend(
+                    process_info.ancestors[..known_ancestor]
+                        .iter()
+                        .map(|(pid, _)| *pid),
+                );
+            }
+        }
+    }
+    // the pids of the descendants that exited are given to other processes sooner or later, so they
+    // are forgotten once no remembered socket is owned by them anymore
+    fn forget_exited(
+        &mut self,
+        sockets_to_procs: &HashMap<LocalSocket, ProcessInfo>,
+        running_pids: &HashSet<u32>,
+    ) {
+        let owners: HashSet<u32> = sockets_to_procs
+            .values()
+            .map(|process_info| process_info.pid)
+            .collect();
+        let roots = &self.roots;
+        self.pids.retain(|pid| {
+            roots.contains(pid) || running_pids.contains(pid) || owners.contains(pid)
+        });
+    }
+    pub fn retain_processes(
+        &mut self,
+        network_utilization: &mut Utilization,
+        sockets_to_procs: &HashMap<LocalSocket, ProcessInfo>,
+        running_pids: &HashSet<u32>,
+    ) {
+        self.add_descendants(sockets_to_procs);
+        self.forget_exited(sockets_to_procs, running_pids);
+        let pids = &self.pids;
+        network_utilization.connections.retain(|connection, _| {
+            get_socket_owner(sockets_to_procs, &connection.local_socket)
+                .map_or(false, |process_info| pids.contains(&process_info.pid))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{Connection, ConnectionInfo, Protocol};
+    use ::std::net::{Ipv4Addr, SocketAddr};
+
+    fn connection(port: u16) -> Connection {
+        Connection::new(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 443),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            port,
+            Protocol::Tcp,
+        )
+    }
+
+    fn utilization(port: u16) -> Utilization {
+        let mut utilization = Utilization::new();
+        utilization.connections.insert(
+            connection(port),
+            ConnectionInfo {
+                interface_name: String::from("eth0"),
+                total_bytes_downloaded: 0,
+                total_bytes_uploaded: 100,
+            },
+        );
+        utilization
+    }
+
+    fn sockets_to_procs(
+        port: u16,
+        pid: u32,
+        ancestors: &[u32],
+    ) -> HashMap<LocalSocket, ProcessInfo> {
+        let process_info = ProcessInfo {
+            pid,
+            ancestors: ancestors.iter().map(|pid| (*pid, String::new())).collect(),
+            ..Default::default()
+        };
+        vec![(connection(port).local_socket, process_info)]
+            .into_iter()
+            .collect()
+    }
+
+    fn pids(pids: &[u32]) -> HashSet<u32> {
+        pids.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_descendant_is_kept_once_its_parent_exits() {
+        let mut filter = ProcessTreeFilter::new(&[100]);
+        filter.add_descendants(&sockets_to_procs(4434, 102, &[101, 100, 1]));
+        let mut counted = utilization(4434);
+        filter.retain_processes(
+            &mut counted,
+            &sockets_to_procs(4434, 102, &[1]),
+            &pids(&[1, 102]),
+        );
+        assert_eq!(counted.connections.len(), 1);
+    }
+
+    #[test]
+    fn test_exited_descendant_is_forgotten() {
+        let mut filter = ProcessTreeFilter::new(&[100]);
+        filter.add_descendants(&sockets_to_procs(4434, 101, &[100, 1]));
+        // its socket is still remembered, so its traffic is counted
+        let mut counted = utilization(4434);
+        filter.retain_processes(
+            &mut counted,
+            &sockets_to_procs(4434, 101, &[100, 1]),
+            &pids(&[1]),
+        );
+        assert_eq!(counted.connections.len(), 1);
+        filter.retain_processes(&mut Utilization::new(), &HashMap::new(), &pids(&[1]));
+        // the pid is then given to a process outside of the tree
+        let mut not_counted = utilization(4435);
+        filter.retain_processes(
+            &mut not_counted,
+            &sockets_to_procs(4435, 101, &[1]),
+            &pids(&[1, 101]),
+        );
+        assert!(not_counted.connections.is_empty());
+    }
+}
@@ -1,8 +1,18 @@
+mod bpf;
 mod connection;
 pub mod dns;
+mod filter;
+mod pcap;
 mod sniffer;
+mod socket_cache;
+mod socket_counters;
 mod utilization;
 
+pub use bpf::*;
 pub use connection::*;
+pub use filter::*;
+pub use pcap::*;
 pub use sniffer::*;
+pub use socket_cache::*;
+pub use socket_counters::*;
 pub use utilization::*;
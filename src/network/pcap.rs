@@ -0,0 +1,427 @@
+use ::std::io::{self, ErrorKind, Read};
+use ::std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use ::std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError};
+use ::std::thread;
+use ::std::time::Duration;
+
+use ::ipnetwork::IpNetwork;
+use ::pnet_bandwhich_fork::datalink::{DataLinkReceiver, NetworkInterface};
+
+// See https://www.tcpdump.org/manpages/pcap-savefile.5.txt
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+
+// See https://www.ietf.org/archive/id/draft-tuexen-opsawg-pcapng-05.html
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const PCAPNG_OBSOLETE_PACKET: u32 = 0x0000_0002;
+const PCAPNG_SIMPLE_PACKET: u32 = 0x0000_0003;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+
+const PCAPNG_OPTION_END: u16 = 0;
+const PCAPNG_OPTION_IF_NAME: u16 = 2;
+const PCAPNG_OPTION_IF_IPV4_ADDR: u16 = 4;
+const PCAPNG_OPTION_IF_IPV6_ADDR: u16 = 5;
+const PCAPNG_OPTION_IF_TSRESOL: u16 = 9;
+const PCAPNG_OPTION_IF_TSOFFSET: u16 = 14;
+
+// See https://www.tcpdump.org/linktypes.html
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const MICROS_PER_SECOND: u64 = 1_000_000;
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+// the largest snapshot length libpcap captures with, along with room for the headers and options
+// of a pcapng block. Longer records can only come from a corrupt capture
+const MAX_RECORD_LENGTH: usize = 256 * 1024 + 4096;
+
+// same as the read timeout of live network interfaces
+const STREAM_READ_TIMEOUT: Duration = Duration::from_secs(1);
+const STREAM_CHANNEL_SIZE: usize = 1_000;
+
+#[derive(Clone, Copy)]
+enum Format {
+    Pcap,
+    Pcapng,
+}
+
+struct CaptureInterface {
+    network_interface: NetworkInterface,
+    link_type: u32,
+    units_per_second: u64,
+    offset_seconds: u64,
+}
+
+pub struct Record<'a> {
+    pub network_interface: &'a NetworkInterface,
+    pub timestamp: Duration,
+    pub data: &'a [u8],
+}
+
+pub struct PcapReader<R> {
+    reader: R,
+    format: Format,
+    big_endian: bool,
+    interfaces: Vec<CaptureInterface>,
+    section_start: usize,
+    local_ips: Vec<IpNetwork>,
+    default_name: String,
+    buffer: Vec<u8>,
+}
+
+pub type CaptureFile = PcapReader<Box<dyn Read + Send>>;
+
+pub struct PcapStream {
+    frames: Receiver<Vec<u8>>,
+    frame: Vec<u8>,
+}
+
+fn link_layer_header_length(link_type: u32) -> usize {
+    // the sniffer understands raw IP and ethernet frames, anything
+    // else gets its pseudo header stripped before reaching it
+    match link_type {
+        LINKTYPE_NULL | LINKTYPE_LOOP => 4,
+        LINKTYPE_LINUX_SLL => 16,
+        LINKTYPE_LINUX_SLL2 => 20,
+        _ => 0,
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+impl<R> PcapReader<R>
+where
+    R: Read,
+{
+    pub fn new(mut reader: R, default_name: &str) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        let mut pcap_reader = PcapReader {
+            reader,
+            format: Format::Pcap,
+            big_endian: false,
+            interfaces: Vec::new(),
+            section_start: 0,
+            local_ips: Vec::new(),
+            default_name: default_name.to_string(),
+            buffer: Vec::new(),
+        };
+        if u32::from_le_bytes(magic) == PCAPNG_SECTION_HEADER {
+            pcap_reader.format = Format::Pcapng;
+            pcap_reader.read_section_header()?;
+        } else {
+            pcap_reader.read_file_header(magic)?;
+        }
+        Ok(pcap_reader)
+    }
+    pub fn with_local_ips(mut self, local_ips: Vec<IpNetwork>) -> Self {
+        for interface in self.interfaces.iter_mut() {
+            interface
+                .network_interface
+                .ips
+                .extend(local_ips.iter().cloned());
+        }
+        self.local_ips = local_ips;
+        self
+    }
+    pub fn next_record(&mut self) -> io::Result<Option<Record>> {
+        let record = match self.format {
+            Format::Pcap => self.read_pcap_record(),
+            Format::Pcapng => self.read_pcapng_record(),
+        };
+        let (interface_id, timestamp, start, length) = match record {
+            Ok(record) => record,
+            // captures cut short (eg. by killing tcpdump) are treated as complete
+            Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let interface = self
+            .interfaces
+            .get(interface_id)
+            .ok_or_else(|| invalid_data("packet refers to an unknown interface"))?;
+        let header_length = link_layer_header_length(interface.link_type).min(length);
+        let timestamp = Duration::from_secs(interface.offset_seconds)
+            + Duration::from_secs(timestamp / interface.units_per_second)
+            + Duration::from_nanos(
+                ((timestamp % interface.units_per_second) as u128 * NANOS_PER_SECOND as u128
+                    / interface.units_per_second as u128) as u64,
+            );
+        Ok(Some(Record {
+            network_interface: &interface.network_interface,
+            timestamp,
+            data: &self.buffer[start + header_length..start + length],
+        }))
+    }
+    fn read_u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+    fn fill_buffer(&mut self, length: usize) -> io::Result<()> {
+        if length > MAX_RECORD_LENGTH {
+            return Err(invalid_data("capture record is too long"));
+        }
+        self.buffer.resize(length, 0);
+        self.reader.read_exact(&mut self.buffer)
+    }
+    fn add_interface(
+        &mut self,
+        name: String,
+        mut ips: Vec<IpNetwork>,
+        link_type: u32,
+        units_per_second: u64,
+        offset_seconds: u64,
+    ) {
+        ips.extend(self.local_ips.iter().cloned());
+        let index = self.interfaces.len() as u32;
+        self.interfaces.push(CaptureInterface {
+            network_interface: NetworkInterface {
+                name,
+                index,
+                mac: None,
+                ips,
+                flags: 0,
+            },
+            link_type,
+            units_per_second,
+            offset_seconds,
+        });
+    }
+    fn read_file_header(&mut self, magic: [u8; 4]) -> io::Result<()> {
+        let units_per_second = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+            (PCAP_MAGIC_MICROS, _) => MICROS_PER_SECOND,
+            (PCAP_MAGIC_NANOS, _) => NANOS_PER_SECOND,
+            (_, PCAP_MAGIC_MICROS) => {
+                self.big_endian = true;
+                MICROS_PER_SECOND
+            }
+            (_, PCAP_MAGIC_NANOS) => {
+                self.big_endian = true;
+                NANOS_PER_SECOND
+            }
+            _ => return Err(invalid_data("not a pcap or pcapng file")),
+        };
+        // version, thiszone, sigfigs, snaplen and link type
+        self.fill_buffer(20)?;
+        // the upper bits of the link type may carry FCS information
+        let link_type = self.read_u32(&self.buffer[16..20]) & 0x0fff_ffff;
+        let name = self.default_name.clone();
+        self.add_interface(name, Vec::new(), link_type, units_per_second, 0);
+        Ok(())
+    }
+    fn read_pcap_record(&mut self) -> io::Result<(usize, u64, usize, usize)> {
+        let mut header = [0; 16];
+        self.reader.read_exact(&mut header)?;
+        let seconds = self.read_u32(&header[0..4]) as u64;
+        let fraction = self.read_u32(&header[4..8]) as u64;
+        let captured_length = self.read_u32(&header[8..12]) as usize;
+        self.fill_buffer(captured_length)?;
+        let units_per_second = self.interfaces[0].units_per_second;
+        Ok((0, seconds * units_per_second + fraction, 0, captured_length))
+    }
+    fn read_section_header(&mut self) -> io::Result<()> {
+        let mut header = [0; 8];
+        self.reader.read_exact(&mut header)?;
+        let byte_order_magic = [header[4], header[5], header[6], header[7]];
+        self.big_endian = match (
+            u32::from_le_bytes(byte_order_magic),
+            u32::from_be_bytes(byte_order_magic),
+        ) {
+            (PCAPNG_BYTE_ORDER_MAGIC, _) => false,
+            (_, PCAPNG_BYTE_ORDER_MAGIC) => true,
+            _ => return Err(invalid_data("invalid pcapng byte order magic")),
+        };
+        let block_length = self.read_u32(&header[0..4]) as usize;
+        if block_length < 12 {
+            return Err(invalid_data("invalid pcapng section header length"));
+        }
+        // interface ids are scoped to the section they were described in
+        self.section_start = self.interfaces.len();
+        self.fill_buffer(block_length - 12)
+    }
+    fn read_interface_description(&mut self) -> io::Result<()> {
+        if self.buffer.len() < 8 {
+            return Err(invalid_data("invalid pcapng interface description"));
+        }
+        let link_type = self.read_u16(&self.buffer[0..2]) as u32;
+        let mut name = format!("{}{}", self.default_name, self.interfaces.len());
+        let mut ips = Vec::new();
+        let mut units_per_second = MICROS_PER_SECOND;
+        let mut offset_seconds = 0;
+        let mut position = 8;
+        while position + 4 <= self.buffer.len() {
+            let code = self.read_u16(&self.buffer[position..position + 2]);
+            let length = self.read_u16(&self.buffer[position + 2..position + 4]) as usize;
+            let value_start = position + 4;
+            let value_end = (value_start + length).min(self.buffer.len());
+            let value = &self.buffer[value_start..value_end];
+            match code {
+                PCAPNG_OPTION_END => break,
+                PCAPNG_OPTION_IF_NAME => {
+                    name = String::from_utf8_lossy(value)
+                        .trim_end_matches('\0')
+                        .to_string();
+                }
+                PCAPNG_OPTION_IF_IPV4_ADDR if value.len() >= 8 => {
+                    let ip = Ipv4Addr::new(value[0], value[1], value[2], value[3]);
+                    let prefix = u32::from_be_bytes([value[4], value[5], value[6], value[7]])
+                        .count_ones() as u8;
+                    if let Ok(network) = IpNetwork::new(IpAddr::V4(ip), prefix) {
+                        ips.push(network);
+                    }
+                }
+                PCAPNG_OPTION_IF_IPV6_ADDR if value.len() >= 17 => {
+                    let mut octets = [0; 16];
+                    octets.copy_from_slice(&value[0..16]);
+                    let ip = Ipv6Addr::from(octets);
+                    if let Ok(network) = IpNetwork::new(IpAddr::V6(ip), value[16]) {
+                        ips.push(network);
+                    }
+                }
+                PCAPNG_OPTION_IF_TSRESOL if !value.is_empty() => {
+                    let exponent = u32::from(value[0] & 0x7f);
+                    let base: u64 = if value[0] & 0x80 == 0 { 10 } else { 2 };
+                    units_per_second = base
+                        .checked_pow(exponent)
+                        .ok_or_else(|| invalid_data("unsupported pcapng timestamp resolution"))?;
+                }
+                PCAPNG_OPTION_IF_TSOFFSET if value.len() >= 8 => {
+                    let mut bytes = [0; 8];
+                    bytes.copy_from_slice(&value[0..8]);
+                    offset_seconds = if self.big_endian {
+                        u64::from_be_bytes(bytes)
+                    } else {
+                        u64::from_le_bytes(bytes)
+                    };
+                }
+                _ => (),
+            }
+            // option values are padded to 32 bits
+            position = value_start + ((length + 3) & !3);
+        }
+        self.add_interface(name, ips, link_type, units_per_second, offset_seconds);
+        Ok(())
+    }
+    fn read_pcapng_record(&mut self) -> io::Result<(usize, u64, usize, usize)> {
+        loop {
+            let mut block_type = [0; 4];
+            self.reader.read_exact(&mut block_type)?;
+            let block_type = self.read_u32(&block_type);
+            if block_type == PCAPNG_SECTION_HEADER {
+                // a new section may switch byte order, so it has to be read before its length
+                self.read_section_header()?;
+                continue;
+            }
+            let mut block_length = [0; 4];
+            self.reader.read_exact(&mut block_length)?;
+            let block_length = self.read_u32(&block_length) as usize;
+            if block_length < 12 {
+                return Err(invalid_data("invalid pcapng block length"));
+            }
+            // the block body, followed by the trailing copy of the block length
+            self.fill_buffer(block_length - 8)?;
+            let body_length = block_length - 12;
+            match block_type {
+                PCAPNG_INTERFACE_DESCRIPTION => {
+                    self.buffer.truncate(body_length);
+                    self.read_interface_description()?;
+                }
+                PCAPNG_ENHANCED_PACKET if body_length >= 20 => {
+                    let interface_id = self.read_u32(&self.buffer[0..4]) as usize;
+                    let timestamp = (self.read_u32(&self.buffer[4..8]) as u64) << 32
+                        | self.read_u32(&self.buffer[8..12]) as u64;
+                    let captured_length = self.read_u32(&self.buffer[12..16]) as usize;
+                    return Ok((
+                        self.section_start + interface_id,
+                        timestamp,
+                        20,
+                        captured_length.min(body_length - 20),
+                    ));
+                }
+                PCAPNG_OBSOLETE_PACKET if body_length >= 20 => {
+                    let interface_id = self.read_u16(&self.buffer[0..2]) as usize;
+                    let timestamp = (self.read_u32(&self.buffer[4..8]) as u64) << 32
+                        | self.read_u32(&self.buffer[8..12]) as u64;
+                    let captured_length = self.read_u32(&self.buffer[12..16]) as usize;
+                    return Ok((
+                        self.section_start + interface_id,
+                        timestamp,
+                        20,
+                        captured_length.min(body_length - 20),
+                    ));
+                }
+                PCAPNG_SIMPLE_PACKET if body_length >= 4 => {
+                    // simple packets carry no timestamp and always belong to the first interface
+                    let original_length = self.read_u32(&self.buffer[0..4]) as usize;
+                    return Ok((
+                        self.section_start,
+                        0,
+                        4,
+                        original_length.min(body_length - 4),
+                    ));
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl PcapStream {
+    pub fn new<R>(mut pcap_reader: PcapReader<R>) -> io::Result<Self>
+    where
+        R: Read + Send + 'static,
+    {
+        let (tx, rx) = sync_channel(STREAM_CHANNEL_SIZE);
+        // reading blocks until the other end writes something, so it happens on its own
+        // thread to let the sniffer time out like it would on a quiet interface
+        thread::Builder::new()
+            .name("pcap_stream_reader".to_string())
+            .spawn(move || {
+                while let Ok(Some(record)) = pcap_reader.next_record() {
+                    if tx.send(record.data.to_vec()).is_err() {
+                        break;
+                    }
+                }
+            })?;
+        Ok(PcapStream {
+            frames: rx,
+            frame: Vec::new(),
+        })
+    }
+}
+
+impl DataLinkReceiver for PcapStream {
+    fn next(&mut self) -> io::Result<&[u8]> {
+        match self.frames.recv_timeout(STREAM_READ_TIMEOUT) {
+            Ok(frame) => {
+                self.frame = frame;
+                Ok(&self.frame)
+            }
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(
+                ErrorKind::TimedOut,
+                "no packets were received from the stream",
+            )),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "the stream has ended",
+            )),
+        }
+    }
+}
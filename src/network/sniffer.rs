@@ -1,4 +1,5 @@
 use ::std::boxed::Box;
+use ::std::io::ErrorKind;
 
 use ::pnet_bandwhich_fork::datalink::{DataLinkReceiver, NetworkInterface};
 use ::pnet_bandwhich_fork::packet::ethernet::{EtherTypes, EthernetPacket};
@@ -80,6 +81,7 @@ macro_rules! extract_transport_protocol {
 pub struct Sniffer {
     network_interface: NetworkInterface,
     network_frames: Box<dyn DataLinkReceiver>,
+    closed: bool,
 }
 
 impl Sniffer {
@@ -90,14 +92,28 @@ impl Sniffer {
         Sniffer {
             network_interface,
             network_frames,
+            closed: false,
         }
     }
     pub fn next(&mut self) -> Option<Segment> {
-        let bytes = self.network_frames.next().ok()?;
+        let bytes = match self.network_frames.next() {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                // only streams (eg. a pcap read from stdin) ever end, interfaces time out instead
+                self.closed = e.kind() == ErrorKind::UnexpectedEof;
+                return None;
+            }
+        };
+        Self::parse_frame(bytes, &self.network_interface)
+    }
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+    pub fn parse_frame(bytes: &[u8], network_interface: &NetworkInterface) -> Option<Segment> {
         // See https://github.com/libpnet/libpnet/blob/master/examples/packetdump.rs
         // VPN interfaces (such as utun0, utun1, etc) have POINT_TO_POINT bit set to 1
-        let payload_offset = if (self.network_interface.is_loopback()
-            || self.network_interface.is_point_to_point())
+        let payload_offset = if (network_interface.is_loopback()
+            || network_interface.is_point_to_point())
             && cfg!(target_os = "macos")
         {
             // The pnet code for BPF loopback adds a zero'd out Ethernet header
@@ -109,19 +125,19 @@ impl Sniffer {
         let version = ip_packet.get_version();
 
         match version {
-            4 => Self::handle_v4(ip_packet, &self.network_interface),
+            4 => Self::handle_v4(ip_packet, network_interface),
             6 => Self::handle_v6(
                 Ipv6Packet::new(&bytes[payload_offset..])?,
-                &self.network_interface,
+                network_interface,
             ),
             _ => {
                 let pkg = EthernetPacket::new(bytes)?;
                 match pkg.get_ethertype() {
                     EtherTypes::Ipv4 => {
-                        Self::handle_v4(Ipv4Packet::new(pkg.payload())?, &self.network_interface)
+                        Self::handle_v4(Ipv4Packet::new(pkg.payload())?, network_interface)
                     }
                     EtherTypes::Ipv6 => {
-                        Self::handle_v6(Ipv6Packet::new(pkg.payload())?, &self.network_interface)
+                        Self::handle_v6(Ipv6Packet::new(pkg.payload())?, network_interface)
                     }
                     _ => None,
                 }
@@ -0,0 +1,108 @@
+use ::std::collections::HashMap;
+use ::std::time::{Duration, Instant};
+
+use crate::network::{get_socket_owner, LocalSocket};
+use crate::ProcessInfo;
+
+// how long the owner of a socket is remembered once the socket is gone, so that the traffic of a
+// connection that opened and closed in between two polls is still attributed to it
+const SOCKET_OWNER_TTL: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+pub struct SocketCache {
+    owners: HashMap<LocalSocket, ProcessInfo>,
+    last_seen: HashMap<LocalSocket, Instant>,
+    // sockets seen in the traffic that were looked up without finding their owner (eg. those of
+    // forwarded traffic), so that they are not looked up again until they are forgotten
+    unowned: HashMap<LocalSocket, Instant>,
+}
+
+impl SocketCache {
+    // returns the sockets just polled along with those remembered from the previous polls
+    pub fn update(
+        &mut self,
+        sockets_to_procs: HashMap<LocalSocket, ProcessInfo>,
+        now: Instant,
+    ) -> HashMap<LocalSocket, ProcessInfo> {
+        for (local_socket, process_info) in sockets_to_procs {
+            self.owners.insert(local_socket, process_info);
+            self.last_seen.insert(local_socket, now);
+            self.unowned.remove(&local_socket);
+        }
+        let is_fresh = |seen: &Instant| now.saturating_duration_since(*seen) < SOCKET_OWNER_TTL;
+        self.last_seen.retain(|_, seen| is_fresh(seen));
+        self.unowned.retain(|_, seen| is_fresh(seen));
+        let last_seen = &self.last_seen;
+        self.owners
+            .retain(|local_socket, _| last_seen.contains_key(local_socket));
+        self.owners.clone()
+    }
+    // whether a socket seen in the traffic has an owner that is not known yet, in which case the
+    // sockets should be polled again right away rather than at the next tick
+    pub fn should_rescan(&mut self, local_socket: &LocalSocket, now: Instant) -> bool {
+        if get_socket_owner(&self.owners, local_socket).is_some()
+            || self.unowned.contains_key(local_socket)
+        {
+            return false;
+        }
+        self.unowned.insert(*local_socket, now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Protocol;
+    use ::std::net::{IpAddr, Ipv4Addr};
+
+    fn local_socket(port: u16) -> LocalSocket {
+        LocalSocket {
+            ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            port,
+            protocol: Protocol::Tcp,
+        }
+    }
+
+    fn sockets_to_procs(port: u16, pid: u32) -> HashMap<LocalSocket, ProcessInfo> {
+        let process_info = ProcessInfo {
+            pid,
+            ..Default::default()
+        };
+        vec![(local_socket(port), process_info)]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_owner_is_remembered() {
+        let mut socket_cache = SocketCache::default();
+        let start = Instant::now();
+        socket_cache.update(sockets_to_procs(443, 1), start);
+        let remembered = socket_cache.update(HashMap::new(), start + Duration::from_secs(1));
+        assert_eq!(remembered[&local_socket(443)].pid, 1);
+        let forgotten = socket_cache.update(HashMap::new(), start + SOCKET_OWNER_TTL);
+        assert!(forgotten.is_empty());
+    }
+
+    #[test]
+    fn test_new_owner_replaces_old_one() {
+        let mut socket_cache = SocketCache::default();
+        let start = Instant::now();
+        socket_cache.update(sockets_to_procs(443, 1), start);
+        let sockets = socket_cache.update(sockets_to_procs(443, 2), start);
+        assert_eq!(sockets[&local_socket(443)].pid, 2);
+    }
+
+    #[test]
+    fn test_unknown_socket_is_looked_up_once() {
+        let mut socket_cache = SocketCache::default();
+        let start = Instant::now();
+        socket_cache.update(sockets_to_procs(443, 1), start);
+        assert!(!socket_cache.should_rescan(&local_socket(443), start));
+        assert!(socket_cache.should_rescan(&local_socket(4434), start));
+        assert!(!socket_cache.should_rescan(&local_socket(4434), start));
+        socket_cache.update(HashMap::new(), start + SOCKET_OWNER_TTL);
+        assert!(socket_cache.should_rescan(&local_socket(4434), start + SOCKET_OWNER_TTL));
+    }
+}
@@ -0,0 +1,179 @@
+use ::pnet_bandwhich_fork::datalink::NetworkInterface;
+use ::std::collections::HashMap;
+use ::std::net::IpAddr;
+
+use crate::network::{Connection, Direction, Segment};
+
+// the bytes a connection has sent (and had acknowledged) and received over its lifetime
+pub struct SocketCounter {
+    pub connection: Connection,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+// stands in for the sniffers when packets cannot be captured: the traffic of each connection is
+// what its counters grew by since the previous poll, which leaves out udp and what connections
+// closed in between two polls sent last. Only the connections of the given interfaces are counted
+pub struct SocketCounters {
+    get_counters: fn() -> Vec<SocketCounter>,
+    network_interfaces: Vec<NetworkInterface>,
+    previous: Option<HashMap<Connection, (u64, u64)>>,
+}
+
+impl SocketCounters {
+    pub fn new(
+        get_counters: fn() -> Vec<SocketCounter>,
+        network_interfaces: Vec<NetworkInterface>,
+    ) -> Self {
+        SocketCounters {
+            get_counters,
+            network_interfaces,
+            previous: None,
+        }
+    }
+    fn interface_name(&self, ip: IpAddr) -> Option<String> {
+        // the ipv4 connections of dual stack sockets are listed with v4-mapped addresses
+        let ip = match ip {
+            IpAddr::V6(ip) => ip.to_ipv4_mapped().map_or(IpAddr::V6(ip), IpAddr::V4),
+            ip => ip,
+        };
+        self.network_interfaces
+            .iter()
+            .find(|interface| interface.ips.iter().any(|network| network.ip() == ip))
+            .map(|interface| interface.name.clone())
+    }
+    pub fn poll(&mut self) -> Vec<Segment> {
+        let counters: HashMap<Connection, (u64, u64)> = (self.get_counters)()
+            .into_iter()
+            .map(|counter| {
+                (
+                    counter.connection,
+                    (counter.bytes_sent, counter.bytes_received),
+                )
+            })
+            .collect();
+        // what was sent before the first poll is not part of the traffic
+        let previous = match self.previous.replace(counters.clone()) {
+            Some(previous) => previous,
+            None => return Vec::new(),
+        };
+        let mut segments = Vec::new();
+        for (connection, (bytes_sent, bytes_received)) in counters {
+            let interface_name = match self.interface_name(connection.local_socket.ip) {
+                Some(interface_name) => interface_name,
+                None => continue,
+            };
+            let (previous_sent, previous_received) = match previous.get(&connection) {
+                // the counters of a new connection reusing the same addresses start over
+                Some(&(sent, received)) if sent <= bytes_sent && received <= bytes_received => {
+                    (sent, received)
+                }
+                _ => (0, 0),
+            };
+            for (direction, data_length) in &[
+                (Direction::Upload, bytes_sent - previous_sent),
+                (Direction::Download, bytes_received - previous_received),
+            ] {
+                if *data_length > 0 {
+                    segments.push(Segment {
+                        interface_name: interface_name.clone(),
+                        connection,
+                        direction: direction.clone(),
+                        data_length: *data_length as u128,
+                    });
+                }
+            }
+        }
+        segments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Protocol;
+    use ::std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn counter(local_ip: &str, bytes_sent: u64, bytes_received: u64) -> SocketCounter {
+        SocketCounter {
+            connection: Connection::new(
+                "1.1.1.1:12345".parse().unwrap(),
+                local_ip.parse().unwrap(),
+                443,
+                Protocol::Tcp,
+            ),
+            bytes_sent,
+            bytes_received,
+        }
+    }
+
+    fn counters(bytes_sent: u64, bytes_received: u64) -> Vec<SocketCounter> {
+        vec![
+            counter("10.0.0.2", bytes_sent, bytes_received),
+            // the connection of another interface
+            counter("192.168.1.2", bytes_sent * 2, bytes_received * 2),
+        ]
+    }
+
+    static POLLS: AtomicUsize = AtomicUsize::new(0);
+
+    // 100/10 bytes before the first poll, 150/10 then, and a new connection with 20/5 at last
+    fn get_counters() -> Vec<SocketCounter> {
+        match POLLS.fetch_add(1, Ordering::SeqCst) {
+            0 => counters(100, 10),
+            1 => counters(150, 10),
+            _ => counters(20, 5),
+        }
+    }
+
+    fn eth0() -> NetworkInterface {
+        NetworkInterface {
+            name: String::from("eth0"),
+            index: 0,
+            mac: None,
+            ips: vec!["10.0.0.2/24".parse().unwrap()],
+            flags: 0,
+        }
+    }
+
+    static MAPPED_POLLS: AtomicUsize = AtomicUsize::new(0);
+
+    fn get_mapped_counters() -> Vec<SocketCounter> {
+        let polls = MAPPED_POLLS.fetch_add(1, Ordering::SeqCst) as u64;
+        vec![counter("::ffff:10.0.0.2", 100 * (polls + 1), 0)]
+    }
+
+    #[test]
+    fn test_v4_mapped_addresses() {
+        let mut socket_counters = SocketCounters::new(get_mapped_counters, vec![eth0()]);
+        assert!(socket_counters.poll().is_empty());
+
+        let segments = socket_counters.poll();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].data_length, 100);
+        assert_eq!(segments[0].interface_name, "eth0");
+    }
+
+    #[test]
+    fn test_deltas_between_polls() {
+        let mut socket_counters = SocketCounters::new(get_counters, vec![eth0()]);
+        assert!(socket_counters.poll().is_empty());
+
+        let segments = socket_counters.poll();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].direction, Direction::Upload);
+        assert_eq!(segments[0].data_length, 50);
+        assert_eq!(segments[0].interface_name, "eth0");
+
+        let mut segments = socket_counters.poll();
+        segments.sort_by_key(|segment| segment.data_length);
+        let lengths: Vec<_> = segments
+            .iter()
+            .map(|segment| (segment.direction.clone(), segment.data_length))
+            .collect();
+        assert_eq!(
+            lengths,
+            vec![(Direction::Download, 5), (Direction::Upload, 20)]
+        );
+    }
+}
@@ -24,7 +24,9 @@ impl Utilization {
         self.connections.clear();
         clone
     }
-    pub fn update(&mut self, seg: Segment) {
+    // returns whether the connection is new since the last reset
+    pub fn update(&mut self, seg: Segment) -> bool {
+        let is_new = !self.connections.contains_key(&seg.connection);
         let total_bandwidth = self
             .connections
             .entry(seg.connection)
@@ -41,5 +43,6 @@ impl Utilization {
                 total_bandwidth.total_bytes_uploaded += seg.data_length;
             }
         }
+        is_new
     }
 }
@@ -0,0 +1,210 @@
+use ::lazy_static::lazy_static;
+use ::regex::Regex;
+
+use crate::Container;
+
+lazy_static! {
+    // docker and podman (cgroupfs or systemd drivers), containerd and cri-o all name the cgroup of
+    // a container after its 64 hex digits id, possibly behind a runtime prefix and a .scope suffix
+    static ref CONTAINER_ID_REGEX: Regex =
+        Regex::new(r"(?:^|/)(?:[a-z]+(?:-[a-z]+)*-)?([0-9a-f]{64})(?:\.scope)?(?:/|$)").unwrap();
+    // kubernetes nests the cgroups of containers under one per pod, the systemd driver escapes
+    // the dashes of its uid as underscores
+    static ref POD_UID_REGEX: Regex = Regex::new(
+        r"pod([0-9a-f]{8}[-_][0-9a-f]{4}[-_][0-9a-f]{4}[-_][0-9a-f]{4}[-_][0-9a-f]{12})"
+    )
+    .unwrap();
+}
+
+pub fn get_container(cgroup_path: &str) -> Option<Container> {
+    let captures = CONTAINER_ID_REGEX.captures(cgroup_path)?;
+    if captures[0].contains("-conmon-") {
+        // the monitor podman runs next to a container, not the container itself
+        return None;
+    }
+    Some(Container {
+        id: captures[1].to_string(),
+        pod_uid: POD_UID_REGEX
+            .captures(cgroup_path)
+            .map(|captures| captures[1].replace('_', "-")),
+    })
+}
+
+// a process has a cgroup in each cgroup v1 hierarchy and/or one in the v2 hierarchy (numbered 0),
+// given with the controllers of their hierarchy: the one naming a container is preferred, then the
+// v2 one, then the one systemd keeps its units in (the others are not laid out by unit), then the
+// first one
+pub fn get_cgroup(cgroups: &[(u32, Vec<String>, String)]) -> (String, Option<Container>) {
+    if let Some((path, container)) = cgroups
+        .iter()
+        .find_map(|(_, _, path)| Some((path, get_container(path)?)))
+    {
+        return (path.clone(), Some(container));
+    }
+    let path = cgroups
+        .iter()
+        .find(|(hierarchy, _, _)| *hierarchy == 0)
+        .or_else(|| {
+            cgroups.iter().find(|(_, controllers, _)| {
+                controllers
+                    .iter()
+                    .any(|controller| controller == "name=systemd")
+            })
+        })
+        .or_else(|| cgroups.first())
+        .map(|(_, _, path)| path.clone())
+        .unwrap_or_default();
+    (path, None)
+}
+
+// the unit of a process is the innermost service or scope of its cgroup, behind the user manager
+// for the units of users, eg. user@1000.service/app.scope
+pub fn get_unit(cgroup_path: &str) -> String {
+    let units: Vec<&str> = cgroup_path
+        .split('/')
+        .filter(|segment| segment.ends_with(".service") || segment.ends_with(".scope"))
+        .collect();
+    match units.as_slice() {
+        [] => String::new(),
+        [user_manager, .., unit] if user_manager.starts_with("user@") => {
+            format!("{}/{}", user_manager, unit)
+        }
+        [.., unit] => unit.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTAINER_ID: &str = "4a1b7a8c2cd3f0b9dbe0b3b4a6e1e2c9f4f2d1c0b9a8e7d6c5b4a3f2e1d0c9b8";
+    const POD_UID: &str = "2f6ba4a3-1c5d-4d4e-9e0e-7a8b9c0d1e2f";
+
+    #[test]
+    fn test_docker_cgroupfs() {
+        let container = get_container(&format!("/docker/{}", CONTAINER_ID)).unwrap();
+        assert_eq!(container.id, CONTAINER_ID);
+        assert_eq!(container.pod_uid, None);
+    }
+
+    #[test]
+    fn test_docker_systemd() {
+        let container =
+            get_container(&format!("/system.slice/docker-{}.scope", CONTAINER_ID)).unwrap();
+        assert_eq!(container.id, CONTAINER_ID);
+    }
+
+    #[test]
+    fn test_kubernetes_cgroupfs() {
+        let container = get_container(&format!(
+            "/kubepods/burstable/pod{}/{}",
+            POD_UID, CONTAINER_ID
+        ))
+        .unwrap();
+        assert_eq!(container.id, CONTAINER_ID);
+        assert_eq!(container.pod_uid.as_deref(), Some(POD_UID));
+    }
+
+    #[test]
+    fn test_kubernetes_systemd() {
+        let container = get_container(&format!(
+            "/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod{}.slice/cri-containerd-{}.scope",
+            POD_UID.replace('-', "_"),
+            CONTAINER_ID
+        ))
+        .unwrap();
+        assert_eq!(container.id, CONTAINER_ID);
+        assert_eq!(container.pod_uid.as_deref(), Some(POD_UID));
+    }
+
+    #[test]
+    fn test_podman_conmon() {
+        let path = format!("/machine.slice/libpod-conmon-{}.scope", CONTAINER_ID);
+        assert!(get_container(&path).is_none());
+    }
+
+    #[test]
+    fn test_not_a_container() {
+        assert!(get_container("/user.slice/user-1000.slice/session-2.scope").is_none());
+        assert!(get_container("/").is_none());
+    }
+
+    #[test]
+    fn test_system_unit() {
+        assert_eq!(get_unit("/system.slice/nginx.service"), "nginx.service");
+        assert_eq!(
+            get_unit("/system.slice/nginx.service/worker"),
+            "nginx.service"
+        );
+    }
+
+    #[test]
+    fn test_user_unit() {
+        assert_eq!(
+            get_unit("/user.slice/user-1000.slice/user@1000.service/app.slice/app.scope"),
+            "user@1000.service/app.scope"
+        );
+        assert_eq!(
+            get_unit("/user.slice/user-1000.slice/session-2.scope"),
+            "session-2.scope"
+        );
+    }
+
+    #[test]
+    fn test_no_unit() {
+        assert_eq!(get_unit("/user.slice"), "");
+        assert_eq!(get_unit("/"), "");
+        assert_eq!(get_unit(""), "");
+    }
+
+    #[test]
+    fn test_get_cgroup_prefers_container() {
+        let cgroups = vec![
+            (0, Vec::new(), String::from("/")),
+            (
+                4,
+                vec![String::from("cpu"), String::from("cpuacct")],
+                format!("/docker/{}", CONTAINER_ID),
+            ),
+        ];
+        let (path, container) = get_cgroup(&cgroups);
+        assert_eq!(path, format!("/docker/{}", CONTAINER_ID));
+        assert_eq!(container.unwrap().id, CONTAINER_ID);
+    }
+
+    #[test]
+    fn test_get_cgroup_prefers_v2() {
+        let cgroups = vec![
+            (4, vec![String::from("memory")], String::from("/user.slice")),
+            (
+                0,
+                Vec::new(),
+                String::from("/user.slice/user-1000.slice/session-2.scope"),
+            ),
+        ];
+        let (path, container) = get_cgroup(&cgroups);
+        assert_eq!(path, "/user.slice/user-1000.slice/session-2.scope");
+        assert!(container.is_none());
+    }
+
+    #[test]
+    fn test_get_cgroup_prefers_systemd_hierarchy_on_v1() {
+        let cgroups = vec![
+            (11, vec![String::from("cpuset")], String::from("/")),
+            (
+                5,
+                vec![String::from("memory")],
+                String::from("/system.slice/sshd.service"),
+            ),
+            (
+                1,
+                vec![String::from("name=systemd")],
+                String::from("/system.slice/sshd.service"),
+            ),
+        ];
+        let (path, container) = get_cgroup(&cgroups);
+        assert_eq!(path, "/system.slice/sshd.service");
+        assert_eq!(get_unit(&path), "sshd.service");
+        assert!(container.is_none());
+    }
+}
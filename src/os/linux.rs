@@ -1,63 +1,415 @@
-use ::std::collections::HashMap;
+use ::std::collections::{HashMap, HashSet};
+use ::std::fs;
+use ::std::net::SocketAddr;
+use ::std::sync::Mutex;
+use ::std::time::SystemTime;
 
-use ::procfs::process::FDTarget;
+use ::lazy_static::lazy_static;
+use ::procfs::process::{FDTarget, Process};
 
-use crate::network::{Connection, Protocol};
-use crate::OpenSockets;
+use super::cgroup::{get_cgroup, get_unit};
+use super::sock_diag::{InetSocket, SockDiag};
+use crate::network::{Connection, Protocol, SocketCounter};
+use crate::{InterfaceCounters, OpenSockets, ProcessInfo};
 
-pub(crate) fn get_open_sockets() -> OpenSockets {
-    let mut open_sockets = HashMap::new();
-    let mut connections = std::vec::Vec::new();
-    let mut inode_to_procname = HashMap::new();
+const PASSWD: &str = "/etc/passwd";
 
-    if let Ok(all_procs) = procfs::process::all_processes() {
-        for process in all_procs {
-            if let Ok(fds) = process.fd() {
-                let procname = process.stat.comm;
-                for fd in fds {
-                    if let FDTarget::Socket(inode) = fd.target {
-                        inode_to_procname.insert(inode, procname.clone());
-                    }
-                }
-            }
+// uids without an entry are shown as they are
+fn read_user_names() -> HashMap<u32, String> {
+    fs::read_to_string(PASSWD)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+// /etc/passwd is only read again once it changes
+#[derive(Default)]
+struct UserNames {
+    modified: Option<SystemTime>,
+    names: HashMap<u32, String>,
+}
+
+impl UserNames {
+    fn update(&mut self) -> &HashMap<u32, String> {
+        let modified = fs::metadata(PASSWD)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_none() || modified != self.modified {
+            self.names = read_user_names();
+            self.modified = modified;
         }
+        &self.names
     }
+}
 
-    if let Ok(mut tcp) = ::procfs::net::tcp() {
-        if let Ok(mut tcp6) = ::procfs::net::tcp6() {
-            tcp.append(&mut tcp6);
+// the parent of a process, then its grandparent and so on up to init, from the parent pid and name
+// of every process
+fn get_ancestors(ppid: i32, parents: &HashMap<i32, (i32, String)>) -> Vec<(u32, String)> {
+    let mut ancestors: Vec<(u32, String)> = Vec::new();
+    let mut pid = ppid;
+    while let Some((ppid, name)) = parents.get(&pid) {
+        // pids are reused, so a process that outlived its parent could seem to be its own ancestor
+        if ancestors
+            .iter()
+            .any(|(ancestor, _)| *ancestor == pid as u32)
+        {
+            break;
         }
-        for entry in tcp.into_iter() {
-            let local_port = entry.local_address.port();
-            let local_ip = entry.local_address.ip();
-            if let (connection, Some(procname)) = (
-                Connection::new(entry.remote_address, local_ip, local_port, Protocol::Tcp),
-                inode_to_procname.get(&entry.inode),
-            ) {
-                open_sockets.insert(connection.local_socket, procname.clone());
-                connections.push(connection);
+        ancestors.push((pid as u32, name.clone()));
+        pid = *ppid;
+    }
+    ancestors
+}
+
+struct IndexedProcess {
+    start_time: i64,
+    owner: u32,
+    // the number of open fds, which is the size linux (since 6.2) reports for the fd directory, or
+    // 0 when it is not reported
+    fd_count: u64,
+    socket_inodes: Vec<u32>,
+    // only looked up for processes with sockets
+    process_info: Option<ProcessInfo>,
+}
+
+// the socket inodes of every process, kept from one poll to the next so that only the fds of new
+// processes and of those that opened or closed some are read again
+#[derive(Default)]
+struct ProcessIndex {
+    processes: HashMap<i32, IndexedProcess>,
+    // sockets no process was found for (eg. those of the kernel), which are not looked for again
+    unowned_inodes: HashSet<u32>,
+}
+
+fn get_fd_count(pid: i32) -> u64 {
+    fs::metadata(format!("/proc/{}/fd", pid))
+        .map(|metadata| metadata.len())
+        .unwrap_or_default()
+}
+
+fn index_process(
+    process: &Process,
+    parents: &HashMap<i32, (i32, String)>,
+    user_names: &HashMap<u32, String>,
+) -> IndexedProcess {
+    let fd_count = get_fd_count(process.pid());
+    let socket_inodes: Vec<_> = process
+        .fd()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|fd| match fd.target {
+            FDTarget::Socket(inode) => Some(inode),
+            _ => None,
+        })
+        .collect();
+    let process_info = if socket_inodes.is_empty() {
+        None
+    } else {
+        let cgroups: Vec<_> = process
+            .cgroups()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|cgroup| (cgroup.hierarchy, cgroup.controllers, cgroup.pathname))
+            .collect();
+        let (cgroup, container) = get_cgroup(&cgroups);
+        Some(ProcessInfo {
+            pid: process.pid() as u32,
+            name: process.stat.comm.clone(),
+            cmdline: process.cmdline().unwrap_or_default().join(" "),
+            exe: process
+                .exe()
+                .map(|exe| exe.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            user: user_names
+                .get(&process.owner)
+                .cloned()
+                .unwrap_or_else(|| process.owner.to_string()),
+            ancestors: get_ancestors(process.stat.ppid, parents),
+            unit: get_unit(&cgroup),
+            cgroup,
+            container,
+        })
+    };
+    IndexedProcess {
+        start_time: process.stat.starttime,
+        owner: process.owner,
+        fd_count,
+        socket_inodes,
+        process_info,
+    }
+}
+
+impl ProcessIndex {
+    fn update(
+        &mut self,
+        all_procs: &[Process],
+        parents: &HashMap<i32, (i32, String)>,
+        user_names: &HashMap<u32, String>,
+    ) {
+        let mut processes = HashMap::new();
+        for process in all_procs {
+            let indexed = match self.processes.remove(&process.pid()) {
+                // older kernels report no size for the fd directory, the processes owning the
+                // sockets that are not known yet are then read again by find_owners
+                Some(mut indexed)
+                    if indexed.start_time == process.stat.starttime
+                        && (indexed.fd_count == 0
+                            || indexed.fd_count == get_fd_count(process.pid())) =>
+                {
+                    // processes are adopted when their parent exits
+                    if let Some(process_info) = indexed.process_info.as_mut() {
+                        process_info.ancestors = get_ancestors(process.stat.ppid, parents);
+                    }
+                    indexed
+                }
+                _ => index_process(process, parents, user_names),
             };
+            processes.insert(process.pid(), indexed);
         }
+        self.processes = processes;
     }
 
-    if let Ok(mut udp) = ::procfs::net::udp() {
-        if let Ok(mut udp6) = ::procfs::net::udp6() {
-            udp.append(&mut udp6);
+    fn inode_to_process(&self) -> HashMap<u32, ProcessInfo> {
+        self.processes
+            .values()
+            .filter_map(|indexed| Some((indexed, indexed.process_info.as_ref()?)))
+            .flat_map(|(indexed, process_info)| {
+                indexed
+                    .socket_inodes
+                    .iter()
+                    .map(move |inode| (*inode, process_info.clone()))
+            })
+            .collect()
+    }
+
+    // a process can close a socket and open another one in between two polls without its fd count
+    // changing, so the processes of the owner of a socket that was not found are read again
+    fn find_owners(
+        &mut self,
+        all_procs: &[Process],
+        unknown_sockets: &[&InetSocket],
+        parents: &HashMap<i32, (i32, String)>,
+        user_names: &HashMap<u32, String>,
+    ) {
+        let unknown_sockets: Vec<_> = unknown_sockets
+            .iter()
+            .filter(|socket| !self.unowned_inodes.contains(&socket.inode))
+            .collect();
+        if unknown_sockets.is_empty() {
+            return;
         }
-        for entry in udp.into_iter() {
-            let local_port = entry.local_address.port();
-            let local_ip = entry.local_address.ip();
-            if let (connection, Some(procname)) = (
-                Connection::new(entry.remote_address, local_ip, local_port, Protocol::Udp),
-                inode_to_procname.get(&entry.inode),
-            ) {
-                open_sockets.insert(connection.local_socket, procname.clone());
-                connections.push(connection);
-            };
+        // the owner is not known when the sockets come from /proc/net
+        let owners: Option<HashSet<u32>> =
+            unknown_sockets.iter().map(|socket| socket.uid).collect();
+        for process in all_procs {
+            if let Some(indexed) = self.processes.get_mut(&process.pid()) {
+                if owners
+                    .as_ref()
+                    .map_or(true, |owners| owners.contains(&indexed.owner))
+                {
+                    *indexed = index_process(process, parents, user_names);
+                }
+            }
         }
+        self.unowned_inodes
+            .extend(unknown_sockets.iter().map(|socket| socket.inode));
+    }
+}
+
+lazy_static! {
+    static ref PROCESS_INDEX: Mutex<ProcessIndex> = Mutex::new(ProcessIndex::default());
+    static ref USER_NAMES: Mutex<UserNames> = Mutex::new(UserNames::default());
+}
+
+fn get_inet_sockets(protocol: Protocol) -> Vec<InetSocket> {
+    // the tables of /proc/net are the fallback for kernels without inet_diag (or udp_diag)
+    if let Ok(sockets) = SockDiag::new().and_then(|mut sock_diag| sock_diag.get_sockets(protocol)) {
+        return sockets;
+    }
+    let entries: Vec<(SocketAddr, SocketAddr, u32)> = match protocol {
+        Protocol::Tcp => ::procfs::net::tcp()
+            .into_iter()
+            .chain(::procfs::net::tcp6())
+            .flatten()
+            .map(|entry| (entry.local_address, entry.remote_address, entry.inode))
+            .collect(),
+        Protocol::Udp => ::procfs::net::udp()
+            .into_iter()
+            .chain(::procfs::net::udp6())
+            .flatten()
+            .map(|entry| (entry.local_address, entry.remote_address, entry.inode))
+            .collect(),
+    };
+    entries
+        .into_iter()
+        .map(|(local_address, remote_address, inode)| InetSocket {
+            local_address,
+            remote_address,
+            inode,
+            uid: None,
+            byte_counts: None,
+        })
+        .collect()
+}
+
+pub(crate) fn get_open_sockets() -> OpenSockets {
+    let mut open_sockets = HashMap::new();
+    let mut connections = std::vec::Vec::new();
+    let mut user_names = USER_NAMES.lock().unwrap();
+    let user_names = user_names.update();
+    let all_procs = procfs::process::all_processes().unwrap_or_default();
+    let parents: HashMap<i32, (i32, String)> = all_procs
+        .iter()
+        .map(|process| {
+            (
+                process.pid(),
+                (process.stat.ppid, process.stat.comm.clone()),
+            )
+        })
+        .collect();
+    let mut process_index = PROCESS_INDEX.lock().unwrap();
+    process_index.update(&all_procs, &parents, user_names);
+
+    let sockets: Vec<_> = [Protocol::Tcp, Protocol::Udp]
+        .iter()
+        .flat_map(|protocol| {
+            get_inet_sockets(*protocol)
+                .into_iter()
+                .map(move |socket| (*protocol, socket))
+        })
+        .collect();
+    let mut inode_to_process = process_index.inode_to_process();
+    // sockets without an inode are those of connections in TIME_WAIT, which no process owns
+    let unknown_sockets: Vec<_> = sockets
+        .iter()
+        .map(|(_, socket)| socket)
+        .filter(|socket| socket.inode != 0 && !inode_to_process.contains_key(&socket.inode))
+        .collect();
+    if !unknown_sockets.is_empty() {
+        process_index.find_owners(&all_procs, &unknown_sockets, &parents, user_names);
+        inode_to_process = process_index.inode_to_process();
+    }
+    let inodes: HashSet<u32> = sockets.iter().map(|(_, socket)| socket.inode).collect();
+    process_index
+        .unowned_inodes
+        .retain(|inode| inodes.contains(inode) && !inode_to_process.contains_key(inode));
+
+    for (protocol, socket) in sockets {
+        if let (connection, Some(process_info)) = (
+            Connection::new(
+                socket.remote_address,
+                socket.local_address.ip(),
+                socket.local_address.port(),
+                protocol,
+            ),
+            inode_to_process.get(&socket.inode),
+        ) {
+            open_sockets.insert(connection.local_socket, process_info.clone());
+            connections.push(connection);
+        };
     }
     OpenSockets {
         sockets_to_procs: open_sockets,
         connections,
+        pids: all_procs
+            .iter()
+            .map(|process| process.pid() as u32)
+            .collect(),
+    }
+}
+
+// the traffic of tcp connections as counted by the kernel, for when packets cannot be captured
+pub(crate) fn get_tcp_counters() -> Vec<SocketCounter> {
+    SockDiag::new()
+        .and_then(|mut sock_diag| sock_diag.get_tcp_byte_counts())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|socket| {
+            let (bytes_sent, bytes_received) = socket.byte_counts?;
+            Some(SocketCounter {
+                connection: Connection::new(
+                    socket.remote_address,
+                    socket.local_address.ip(),
+                    socket.local_address.port(),
+                    Protocol::Tcp,
+                ),
+                bytes_sent,
+                bytes_received,
+            })
+        })
+        .collect()
+}
+
+// the lines of /proc/net/dev after its two header lines, eg.
+//   eth0: 1000 10 0 0 0 0 0 0 2000 20 0 0 0 0 0 0
+// with the bytes, packets, errors and drops received and 4 more counters, then the same sent
+fn parse_net_dev(net_dev: &str) -> HashMap<String, InterfaceCounters> {
+    net_dev
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (interface_name, counters) = line.split_at(line.find(':')?);
+            let counters: Vec<u64> = counters[1..]
+                .split_whitespace()
+                .map(|counter| counter.parse().ok())
+                .collect::<Option<_>>()?;
+            if counters.len() < 12 {
+                return None;
+            }
+            let interface_counters = InterfaceCounters {
+                bytes_received: counters[0],
+                packets_received: counters[1],
+                bytes_sent: counters[8],
+                packets_sent: counters[9],
+                errors: counters[2] + counters[10],
+                drops: counters[3] + counters[11],
+                speed: None,
+            };
+            Some((interface_name.trim().to_string(), interface_counters))
+        })
+        .collect()
+}
+
+pub(crate) fn get_interface_counters() -> HashMap<String, InterfaceCounters> {
+    let mut interface_counters =
+        parse_net_dev(&fs::read_to_string("/proc/net/dev").unwrap_or_default());
+    for (interface_name, counters) in interface_counters.iter_mut() {
+        // virtual interfaces have no speed (or -1), nor have those whose link is down
+        counters.speed = fs::read_to_string(format!("/sys/class/net/{}/speed", interface_name))
+            .ok()
+            .and_then(|speed| speed.trim().parse::<i64>().ok())
+            .filter(|speed| *speed > 0)
+            .map(|speed| speed as u64);
+    }
+    interface_counters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_net_dev() {
+        let net_dev = "Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:   12345      67    0    0    0     0          0         0    12345      67    0    0    0     0       0          0
+  eth0: 98765432  123456    1    2    0     0          0        10 23456789   65432    3    4    0     0       0          0
+";
+        let interface_counters = parse_net_dev(net_dev);
+        assert_eq!(interface_counters.len(), 2);
+        let eth0 = &interface_counters["eth0"];
+        assert_eq!(eth0.bytes_received, 98765432);
+        assert_eq!(eth0.packets_received, 123456);
+        assert_eq!(eth0.bytes_sent, 23456789);
+        assert_eq!(eth0.packets_sent, 65432);
+        assert_eq!(eth0.errors, 4);
+        assert_eq!(eth0.drops, 6);
+        assert_eq!(interface_counters["lo"].bytes_sent, 12345);
     }
 }
@@ -1,7 +1,7 @@
-use ::std::collections::HashMap;
+use ::std::collections::{HashMap, HashSet};
 
 use crate::network::Connection;
-use crate::OpenSockets;
+use crate::{InterfaceCounters, OpenSockets, ProcessInfo};
 
 use super::lsof_utils;
 use std::net::SocketAddr;
@@ -15,11 +15,33 @@ struct RawConnection {
     process_name: String,
 }
 
+// the parent of a process, then its grandparent and so on up to launchd, from the parent pid and
+// name of every process
+fn get_ancestors(pid: u32, parents: &HashMap<u32, (u32, String)>) -> Vec<(u32, String)> {
+    let mut ancestors: Vec<(u32, String)> = Vec::new();
+    let mut pid = match parents.get(&pid) {
+        Some((ppid, _)) => *ppid,
+        None => return ancestors,
+    };
+    while let Some((ppid, name)) = parents.get(&pid) {
+        // pids are reused, so a process that outlived its parent could seem to be its own ancestor
+        if pid == 0 || ancestors.iter().any(|(ancestor, _)| *ancestor == pid) {
+            break;
+        }
+        ancestors.push((pid, name.clone()));
+        pid = *ppid;
+    }
+    ancestors
+}
+
 pub(crate) fn get_open_sockets() -> OpenSockets {
     let mut open_sockets = HashMap::new();
     let mut connections_vec = std::vec::Vec::new();
 
     let connections = lsof_utils::get_connections();
+    // without ps, the processes are listed on their own and only those with sockets are known
+    let parents = lsof_utils::get_parents().unwrap_or_default();
+    let mut pids: HashSet<u32> = parents.keys().cloned().collect();
 
     for raw_connection in connections {
         let protocol = raw_connection.get_protocol();
@@ -31,12 +53,31 @@ pub(crate) fn get_open_sockets() -> OpenSockets {
         let socket_addr = SocketAddr::new(remote_ip, remote_port);
         let connection = Connection::new(socket_addr, local_ip, local_port, protocol);
 
-        open_sockets.insert(connection.local_socket, raw_connection.process_name.clone());
+        // lsof does not list the command lines or the executables
+        let process_info = ProcessInfo {
+            pid: raw_connection.pid,
+            name: raw_connection.process_name.clone(),
+            ancestors: get_ancestors(raw_connection.pid, &parents),
+            cmdline: String::new(),
+            exe: String::new(),
+            user: raw_connection.user.clone(),
+            cgroup: String::new(),
+            unit: String::new(),
+            container: None,
+        };
+        pids.insert(process_info.pid);
+        open_sockets.insert(connection.local_socket, process_info);
         connections_vec.push(connection);
     }
 
     OpenSockets {
         sockets_to_procs: open_sockets,
         connections: connections_vec,
+        pids,
     }
 }
+
+// the counters of the interfaces are only read on linux for now
+pub(crate) fn get_interface_counters() -> HashMap<String, InterfaceCounters> {
+    HashMap::new()
+}
@@ -1,7 +1,9 @@
 use crate::network::Protocol;
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::collections::HashMap;
 use std::ffi::OsStr;
+use std::io;
 use std::net::IpAddr;
 use std::process::Command;
 
@@ -13,12 +15,15 @@ pub struct RawConnection {
     remote_port: String,
     protocol: String,
     pub process_name: String,
+    pub pid: u32,
+    pub user: String,
 }
 
 lazy_static! {
     static ref CONNECTION_REGEX: Regex =
         Regex::new(r"\[?([^\s\]]*)\]?:(\d+)->\[?([^\s\]]*)\]?:(\d+)").unwrap();
     static ref LISTEN_REGEX: Regex = Regex::new(r"\[?([^\s\[\]]*)\]?:(.*)").unwrap();
+    static ref PROCESS_REGEX: Regex = Regex::new(r"^\s*(\d+)\s+(\d+)\s+(.*)$").unwrap();
 }
 
 fn get_null_addr(ip_type: &str) -> &str {
@@ -38,9 +43,9 @@ impl RawConnection {
             return None;
         }
         let process_name = columns[0].replace("\\x20", " ");
+        let pid = columns[1].parse().ok()?;
+        let user = columns[2].to_string();
         // Unneeded
-        // let pid = columns[1];
-        // let username = columns[2];
         // let fd = columns[3];
 
         // IPv4 or IPv6
@@ -72,6 +77,8 @@ impl RawConnection {
                 remote_port,
                 protocol,
                 process_name,
+                pid,
+                user,
             };
             Some(connection)
         } else if let Some(caps) = LISTEN_REGEX.captures(connection_str) {
@@ -95,6 +102,8 @@ impl RawConnection {
                 remote_port,
                 protocol,
                 process_name,
+                pid,
+                user,
             };
             Some(connection)
         } else {
@@ -141,6 +150,35 @@ where
     String::from_utf8_lossy(&output.stdout).into_owned()
 }
 
+// the parent pid and the name of every process by its pid
+pub fn get_parents() -> io::Result<HashMap<u32, (u32, String)>> {
+    let output = Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,ucomm="])
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("ps exited with {}", output.status),
+        ));
+    }
+    Ok(parse_parents(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_parents(content: &str) -> HashMap<u32, (u32, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            // Example row
+            //   664     1 com.apple.WebKit
+            let caps = PROCESS_REGEX.captures(line)?;
+            let pid = caps.get(1)?.as_str().parse().ok()?;
+            let ppid = caps.get(2)?.as_str().parse().ok()?;
+            let name = caps.get(3)?.as_str().trim_end().to_string();
+            Some((pid, (ppid, name)))
+        })
+        .collect()
+}
+
 pub struct RawConnections {
     content: Vec<RawConnection>,
 }
@@ -177,6 +215,14 @@ com.apple   590 etoledom  203u  IPv4 0x28ffb9c04110ea8f      0t0  TCP 192.168.1.
 com.apple   590 etoledom  204u  IPv4 0x28ffb9c04111253f      0t0  TCP 192.168.1.37:60374->140.82.114.26:443
 "#;
 
+    #[test]
+    fn test_parse_parents() {
+        let parents = parse_parents("    1     0 launchd\n  664     1 Google Chrome\n  bogus\n");
+        assert_eq!(parents.len(), 2);
+        assert_eq!(parents[&1], (0, String::from("launchd")));
+        assert_eq!(parents[&664], (1, String::from("Google Chrome")));
+    }
+
     #[test]
     fn test_iterator_multiline() {
         let iterator = RawConnections::new(String::from(FULL_RAW_OUTPUT));
@@ -267,4 +313,29 @@ com.apple   590 etoledom  204u  IPv4 0x28ffb9c04111253f      0t0  TCP 192.168.1.
         let connection = RawConnection::new(raw_line).unwrap();
         assert_eq!(connection.process_name, String::from("ProcessName"));
     }
+
+    #[test]
+    fn test_raw_connection_parse_pid_ipv4() {
+        test_raw_connection_parse_pid(LINE_RAW_OUTPUT);
+    }
+    #[test]
+    fn test_raw_connection_parse_pid_ipv6() {
+        test_raw_connection_parse_pid(IPV6_LINE_RAW_OUTPUT);
+    }
+    fn test_raw_connection_parse_pid(raw_line: &str) {
+        let connection = RawConnection::new(raw_line).unwrap();
+        assert_eq!(connection.pid, 29266);
+    }
+    #[test]
+    fn test_raw_connection_parse_user_ipv4() {
+        test_raw_connection_parse_user(LINE_RAW_OUTPUT);
+    }
+    #[test]
+    fn test_raw_connection_parse_user_ipv6() {
+        test_raw_connection_parse_user(IPV6_LINE_RAW_OUTPUT);
+    }
+    fn test_raw_connection_parse_user(raw_line: &str) {
+        let connection = RawConnection::new(raw_line).unwrap();
+        assert_eq!(connection.user, String::from("user"));
+    }
 }
@@ -1,5 +1,11 @@
 #[cfg(target_os = "linux")]
+mod cgroup;
+#[cfg(target_os = "linux")]
 pub(self) mod linux;
+#[cfg(target_os = "linux")]
+mod packet_socket;
+#[cfg(target_os = "linux")]
+mod sock_diag;
 
 #[cfg(any(target_os = "macos", target_os = "freebsd"))]
 pub(self) mod lsof;
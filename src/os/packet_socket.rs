@@ -0,0 +1,192 @@
+use ::pnet_bandwhich_fork::datalink::{DataLinkReceiver, NetworkInterface};
+use ::std::fs;
+use ::std::io;
+use ::std::mem;
+use ::std::sync::Arc;
+
+use crate::network::{BpfInstruction, LinkType};
+
+// from linux/if_packet.h
+const PACKET_STATISTICS: libc::c_int = 6;
+// from linux/if_arp.h
+const ARPHRD_ETHER: u16 = 1;
+const ARPHRD_PPP: u16 = 512;
+const ARPHRD_RAWIP: u16 = 519;
+const ARPHRD_LOOPBACK: u16 = 772;
+const ARPHRD_NONE: u16 = 0xfffe;
+
+#[repr(C)]
+#[derive(Default)]
+struct PacketStats {
+    packets: u32,
+    drops: u32,
+}
+
+struct SocketFd(libc::c_int);
+
+impl Drop for SocketFd {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
+
+// pnet does not expose the socket of its channels, which is needed to attach a filter and to ask
+// how many packets the kernel dropped, so filtered captures open their own
+pub struct PacketSocket {
+    socket: Arc<SocketFd>,
+    read_buffer: Vec<u8>,
+}
+
+// the packets the kernel dropped because the socket's buffer was full, as it reads them from
+// another thread than the one capturing
+pub struct PacketStatistics {
+    socket: Arc<SocketFd>,
+    drops: u64,
+}
+
+fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+    if result == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+fn set_socket_option<T>(
+    fd: libc::c_int,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: &T,
+) -> io::Result<()> {
+    check(unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            value as *const T as *const libc::c_void,
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    })
+    .map(|_| ())
+}
+
+// the frames of tun, wireguard and ppp interfaces come without a link header, those of other
+// (eg. wireless monitoring) interfaces with headers the filters know nothing about
+pub fn get_link_type(network_interface: &NetworkInterface) -> io::Result<LinkType> {
+    let path = format!("/sys/class/net/{}/type", network_interface.name);
+    let link_type = fs::read_to_string(path)?
+        .trim()
+        .parse::<u16>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    match link_type {
+        ARPHRD_ETHER | ARPHRD_LOOPBACK => Ok(LinkType::Ethernet),
+        ARPHRD_NONE | ARPHRD_PPP | ARPHRD_RAWIP => Ok(LinkType::RawIp),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "BPF filters are not supported on links of type {}",
+                link_type
+            ),
+        )),
+    }
+}
+
+impl PacketSocket {
+    pub fn new(
+        network_interface: &NetworkInterface,
+        bpf_program: &[BpfInstruction],
+    ) -> io::Result<Self> {
+        // the socket starts out without a protocol so it receives nothing until the filter is
+        // attached, otherwise unfiltered frames could be queued in between
+        let fd = check(unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, 0) })?;
+        let socket = PacketSocket {
+            socket: Arc::new(SocketFd(fd)),
+            read_buffer: vec![0; 65536],
+        };
+        let mut filter = bpf_program
+            .iter()
+            .map(|instruction| libc::sock_filter {
+                code: instruction.code,
+                jt: instruction.jt,
+                jf: instruction.jf,
+                k: instruction.k,
+            })
+            .collect::<Vec<_>>();
+        let program = libc::sock_fprog {
+            len: filter.len() as libc::c_ushort,
+            filter: filter.as_mut_ptr(),
+        };
+        set_socket_option(fd, libc::SOL_SOCKET, libc::SO_ATTACH_FILTER, &program)?;
+
+        let mut address: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        address.sll_family = libc::AF_PACKET as libc::c_ushort;
+        address.sll_protocol = (libc::ETH_P_ALL as u16).to_be();
+        address.sll_ifindex = network_interface.index as libc::c_int;
+        check(unsafe {
+            libc::bind(
+                fd,
+                &address as *const libc::sockaddr_ll as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        })?;
+
+        // like the unfiltered channels, reading times out every second
+        let timeout = libc::timeval {
+            tv_sec: 1,
+            tv_usec: 0,
+        };
+        set_socket_option(fd, libc::SOL_SOCKET, libc::SO_RCVTIMEO, &timeout)?;
+        Ok(socket)
+    }
+
+    pub fn statistics(&self) -> PacketStatistics {
+        PacketStatistics {
+            socket: self.socket.clone(),
+            drops: 0,
+        }
+    }
+}
+
+impl PacketStatistics {
+    // the drops since the socket was opened: the kernel starts counting over each time it is asked
+    pub fn drops(&mut self) -> u64 {
+        let mut stats = PacketStats::default();
+        let mut length = mem::size_of::<PacketStats>() as libc::socklen_t;
+        let result = unsafe {
+            libc::getsockopt(
+                self.socket.0,
+                libc::SOL_PACKET,
+                PACKET_STATISTICS,
+                &mut stats as *mut PacketStats as *mut libc::c_void,
+                &mut length,
+            )
+        };
+        if result == 0 {
+            self.drops += u64::from(stats.drops);
+        }
+        self.drops
+    }
+}
+
+impl DataLinkReceiver for PacketSocket {
+    fn next(&mut self) -> io::Result<&[u8]> {
+        let length = unsafe {
+            libc::recv(
+                self.socket.0,
+                self.read_buffer.as_mut_ptr() as *mut libc::c_void,
+                self.read_buffer.len(),
+                0,
+            )
+        };
+        if length == -1 {
+            let error = io::Error::last_os_error();
+            return match error.kind() {
+                io::ErrorKind::WouldBlock => Err(io::Error::new(io::ErrorKind::TimedOut, error)),
+                _ => Err(error),
+            };
+        }
+        Ok(&self.read_buffer[..length as usize])
+    }
+}
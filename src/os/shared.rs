@@ -1,24 +1,37 @@
-use ::pnet_bandwhich_fork::datalink::Channel::Ethernet;
 use ::pnet_bandwhich_fork::datalink::DataLinkReceiver;
-use ::pnet_bandwhich_fork::datalink::{self, Config, NetworkInterface};
-use ::std::io::{self, stdin, ErrorKind, Write};
+use ::pnet_bandwhich_fork::datalink::{self, NetworkInterface};
+use ::std::fs::File;
+use ::std::io::{self, stdin, BufReader, ErrorKind, Read, Write};
+use ::std::net::IpAddr;
+use ::std::path::PathBuf;
 use ::termion::event::Event;
+use ::termion::get_tty;
 use ::termion::input::TermRead;
 use ::tokio::runtime::Runtime;
 
-use ::std::time;
+use ::std::collections::{HashMap, HashSet};
+use ::std::iter;
 
 use crate::os::errors::GetInterfaceErrorKind;
 use signal_hook::iterator::Signals;
 
 #[cfg(target_os = "linux")]
-use crate::os::linux::get_open_sockets;
+use crate::os::linux::{get_interface_counters, get_open_sockets, get_tcp_counters};
 #[cfg(any(target_os = "macos", target_os = "freebsd"))]
-use crate::os::lsof::get_open_sockets;
-use crate::{network::dns, OsInputOutput};
+use crate::os::lsof::{get_interface_counters, get_open_sockets};
+#[cfg(target_os = "linux")]
+use crate::os::packet_socket::{get_link_type, PacketSocket};
+use crate::{
+    network::{compile_bpf, dns, CaptureFile, LinkType, PcapReader, PcapStream, SocketCounters},
+    GetCaptureDrops, InterfaceCounters, OpenSockets, OsInputOutput,
+};
 
 pub type OnSigWinch = dyn Fn(Box<dyn Fn()>) + Send;
 pub type SigCleanup = dyn Fn() + Send;
+type InterfacesAndFrames = (Vec<NetworkInterface>, Vec<Box<dyn DataLinkReceiver>>);
+// how many packets the kernel dropped before they could be read from the channel so far
+type CountDrops = Box<dyn FnMut() -> u64 + Send>;
+type Channel = (Box<dyn DataLinkReceiver>, Option<CountDrops>);
 
 pub struct KeyboardEvents;
 
@@ -32,28 +45,59 @@ impl Iterator for KeyboardEvents {
     }
 }
 
+// the filter is compiled for each interface, as where the ip header starts depends on its link.
+// Filtered captures read from a socket of their own, which also tells how many packets it dropped
+#[cfg(target_os = "linux")]
+fn get_channel(interface: &NetworkInterface, bpf: Option<&str>) -> io::Result<Channel> {
+    let expression = match bpf {
+        Some(expression) => expression,
+        None => return get_unfiltered_channel(interface),
+    };
+    let bpf_program = compile_bpf(expression, get_link_type(interface)?)
+        .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    let packet_socket = PacketSocket::new(interface, &bpf_program)?;
+    let mut statistics = packet_socket.statistics();
+    Ok((
+        Box::new(packet_socket),
+        Some(Box::new(move || statistics.drops())),
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_channel(interface: &NetworkInterface, bpf: Option<&str>) -> io::Result<Channel> {
+    if bpf.is_some() {
+        return Err(io::Error::new(
+            ErrorKind::Other,
+            "BPF filters are only supported on Linux",
+        ));
+    }
+    get_unfiltered_channel(interface)
+}
+
+fn get_unfiltered_channel(interface: &NetworkInterface) -> io::Result<Channel> {
+    let mut config = datalink::Config::default();
+    config.read_timeout = Some(::std::time::Duration::new(1, 0));
+    match datalink::channel(interface, config)? {
+        datalink::Channel::Ethernet(_tx, rx) => Ok((rx, None)),
+        _ => Err(io::Error::new(
+            ErrorKind::Other,
+            "Unsupported interface type",
+        )),
+    }
+}
+
 fn get_datalink_channel(
     interface: &NetworkInterface,
-) -> Result<Box<dyn DataLinkReceiver>, GetInterfaceErrorKind> {
-    let mut config = Config::default();
-    config.read_timeout = Some(time::Duration::new(1, 0));
-
-    match datalink::channel(interface, config) {
-        Ok(Ethernet(_tx, rx)) => Ok(rx),
-        Ok(_) => Err(GetInterfaceErrorKind::OtherError(format!(
-            "{}: Unsupported interface type",
-            interface.name
-        ))),
-        Err(e) => match e.kind() {
-            ErrorKind::PermissionDenied => Err(GetInterfaceErrorKind::PermissionError(
-                interface.name.to_owned(),
-            )),
-            _ => Err(GetInterfaceErrorKind::OtherError(format!(
-                "{}: {}",
-                &interface.name, e
-            ))),
-        },
-    }
+    bpf: Option<&str>,
+) -> Result<Channel, GetInterfaceErrorKind> {
+    let channel = get_channel(interface, bpf);
+
+    channel.map_err(|e| match e.kind() {
+        ErrorKind::PermissionDenied => {
+            GetInterfaceErrorKind::PermissionError(interface.name.to_owned())
+        }
+        _ => GetInterfaceErrorKind::OtherError(format!("{}: {}", &interface.name, e)),
+    })
 }
 
 fn get_interface(interface_name: &str) -> Option<NetworkInterface> {
@@ -62,6 +106,52 @@ fn get_interface(interface_name: &str) -> Option<NetworkInterface> {
         .find(|iface| iface.name == interface_name)
 }
 
+fn get_capture_file(path: &PathBuf, local_ips: &[IpAddr]) -> Result<CaptureFile, failure::Error> {
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("pcap"));
+    let file = match File::open(path) {
+        Ok(file) => Box::new(BufReader::new(file)) as Box<dyn Read + Send>,
+        Err(e) => failure::bail!("Cannot open capture file {}: {}", path.display(), e),
+    };
+    match PcapReader::new(file, &name) {
+        Ok(capture_file) => {
+            Ok(capture_file.with_local_ips(local_ips.iter().map(|ip| (*ip).into()).collect()))
+        }
+        Err(e) => failure::bail!("Cannot read capture file {}: {}", path.display(), e),
+    }
+}
+
+fn get_stdin_frames(local_ips: &[IpAddr]) -> Result<InterfacesAndFrames, failure::Error> {
+    let pcap_reader = match PcapReader::new(stdin(), "stdin") {
+        Ok(pcap_reader) => pcap_reader,
+        Err(e) => failure::bail!("Cannot read a pcap stream from stdin: {}", e),
+    };
+    let network_interface = NetworkInterface {
+        name: String::from("stdin"),
+        index: 0,
+        mac: None,
+        ips: local_ips.iter().map(|ip| (*ip).into()).collect(),
+        flags: 0,
+    };
+    let network_frames = Box::new(PcapStream::new(pcap_reader)?) as Box<dyn DataLinkReceiver>;
+    Ok((vec![network_interface], vec![network_frames]))
+}
+
+// the sockets of this machine have nothing to do with traffic captured elsewhere
+fn get_no_open_sockets() -> OpenSockets {
+    OpenSockets {
+        sockets_to_procs: HashMap::new(),
+        connections: Vec::new(),
+        pids: HashSet::new(),
+    }
+}
+
+fn get_no_interface_counters() -> HashMap<String, InterfaceCounters> {
+    HashMap::new()
+}
+
 fn sigwinch() -> (Box<OnSigWinch>, Box<SigCleanup>) {
     let signals = Signals::new(&[signal_hook::SIGWINCH]).unwrap();
     let on_winch = {
@@ -98,12 +188,7 @@ pub struct UserErrors {
 
 pub fn collect_errors<'a, I>(network_frames: I) -> String
 where
-    I: Iterator<
-        Item = (
-            &'a NetworkInterface,
-            Result<Box<dyn DataLinkReceiver>, GetInterfaceErrorKind>,
-        ),
-    >,
+    I: Iterator<Item = (&'a NetworkInterface, Result<Channel, GetInterfaceErrorKind>)>,
 {
     let errors = network_frames.fold(
         UserErrors {
@@ -163,10 +248,24 @@ where
     }
 }
 
-pub fn get_input(
+// the drops of each interface since the capture started
+fn get_capture_drops(mut count_drops: Vec<(String, CountDrops)>) -> Box<GetCaptureDrops> {
+    Box::new(move || {
+        count_drops
+            .iter_mut()
+            .map(|(interface_name, count_drops)| (interface_name.clone(), count_drops()))
+            .collect()
+    })
+}
+
+fn get_no_capture_drops() -> Box<GetCaptureDrops> {
+    Box::new(HashMap::new)
+}
+
+fn get_network_frames(
     interface_name: &Option<String>,
-    resolve: bool,
-) -> Result<OsInputOutput, failure::Error> {
+    bpf: Option<&str>,
+) -> Result<(InterfacesAndFrames, Box<GetCaptureDrops>), failure::Error> {
     let network_interfaces = if let Some(name) = interface_name {
         match get_interface(&name) {
             Some(interface) => vec![interface],
@@ -182,27 +281,39 @@ pub fn get_input(
     let network_frames = network_interfaces
         .iter()
         .filter(|iface| iface.is_up() && !iface.ips.is_empty())
-        .map(|iface| (iface, get_datalink_channel(iface)));
+        .map(|iface| (iface, get_datalink_channel(iface, bpf)));
 
-    let (available_network_frames, network_interfaces) = {
+    let (available_network_frames, network_interfaces, count_drops) = {
         let network_frames = network_frames.clone();
         let mut available_network_frames = Vec::new();
         let mut available_interfaces: Vec<NetworkInterface> = Vec::new();
-        for (iface, rx) in network_frames.filter_map(|(iface, channel)| {
-            if let Ok(rx) = channel {
-                Some((iface, rx))
+        let mut count_drops = Vec::new();
+        for (iface, (rx, count_interface_drops)) in network_frames.filter_map(|(iface, channel)| {
+            if let Ok(channel) = channel {
+                Some((iface, channel))
             } else {
                 None
             }
         }) {
             available_interfaces.push(iface.clone());
             available_network_frames.push(rx);
+            if let Some(count_interface_drops) = count_interface_drops {
+                count_drops.push((iface.name.clone(), count_interface_drops));
+            }
         }
-        (available_network_frames, available_interfaces)
+        (available_network_frames, available_interfaces, count_drops)
     };
 
     if available_network_frames.is_empty() {
-        let all_errors = collect_errors(network_frames.clone());
+        let errors: Vec<_> = network_frames.clone().collect();
+        let permission_denied = errors
+            .iter()
+            .any(|(_, channel)| matches!(channel, Err(GetInterfaceErrorKind::PermissionError(_))));
+        let all_errors = collect_errors(errors.into_iter());
+        if permission_denied {
+            // told apart from the other errors so that the socket counters can be used instead
+            return Err(GetInterfaceErrorKind::PermissionError(all_errors).into());
+        }
         if !all_errors.is_empty() {
             failure::bail!(all_errors);
         }
@@ -210,7 +321,133 @@ pub fn get_input(
         failure::bail!("Failed to find any network interface to listen on.");
     }
 
-    let keyboard_events = Box::new(KeyboardEvents);
+    Ok((
+        (network_interfaces, available_network_frames),
+        get_capture_drops(count_drops),
+    ))
+}
+
+// packets can only be captured with privileges, while the kernel tells anyone how many bytes each
+// tcp connection sent and received
+#[cfg(target_os = "linux")]
+fn get_socket_counters(
+    interface_name: &Option<String>,
+    error: &failure::Error,
+) -> Option<SocketCounters> {
+    match error.downcast_ref::<GetInterfaceErrorKind>() {
+        Some(GetInterfaceErrorKind::PermissionError(_)) => {}
+        _ => return None,
+    }
+    let network_interfaces = datalink::interfaces()
+        .into_iter()
+        .filter(|iface| {
+            interface_name
+                .as_ref()
+                .map_or(true, |name| iface.name == *name)
+        })
+        .collect();
+    Some(SocketCounters::new(get_tcp_counters, network_interfaces))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_socket_counters(
+    _interface_name: &Option<String>,
+    _error: &failure::Error,
+) -> Option<SocketCounters> {
+    None
+}
+
+pub fn get_input(
+    interface_name: &Option<String>,
+    resolve: bool,
+    pcap_file: &Option<PathBuf>,
+    pcap_stdin: bool,
+    local_ips: &[IpAddr],
+    bpf: &Option<String>,
+) -> Result<OsInputOutput, failure::Error> {
+    // checked once up front, while each interface compiles it for its own link
+    if let Some(expression) = bpf {
+        if let Err(e) = compile_bpf(expression, LinkType::Ethernet) {
+            failure::bail!("Invalid BPF filter \"{}\": {}", expression, e);
+        }
+    }
+    let (
+        network_interfaces,
+        network_frames,
+        capture_file,
+        socket_counters,
+        get_open_sockets,
+        get_capture_drops,
+    ) = if let Some(path) = pcap_file {
+        let capture_file = get_capture_file(path, local_ips)?;
+        (
+            Vec::new(),
+            Vec::new(),
+            Some(capture_file),
+            None,
+            get_no_open_sockets as fn() -> OpenSockets,
+            get_no_capture_drops(),
+        )
+    } else if pcap_stdin {
+        let (network_interfaces, network_frames) = get_stdin_frames(local_ips)?;
+        (
+            network_interfaces,
+            network_frames,
+            None,
+            None,
+            get_no_open_sockets as fn() -> OpenSockets,
+            get_no_capture_drops(),
+        )
+    } else {
+        match get_network_frames(interface_name, bpf.as_deref()) {
+            Ok(((network_interfaces, network_frames), get_capture_drops)) => (
+                network_interfaces,
+                network_frames,
+                None,
+                None,
+                get_open_sockets as fn() -> OpenSockets,
+                get_capture_drops,
+            ),
+            // the counters cannot be filtered with bpf
+            Err(e) => match get_socket_counters(interface_name, &e) {
+                Some(socket_counters) if bpf.is_none() => {
+                    eprintln!(
+                        "Cannot capture packets, counting the traffic of tcp connections only"
+                    );
+                    (
+                        Vec::new(),
+                        Vec::new(),
+                        None,
+                        Some(socket_counters),
+                        get_open_sockets as fn() -> OpenSockets,
+                        get_no_capture_drops(),
+                    )
+                }
+                _ => return Err(e),
+            },
+        }
+    };
+
+    // nor do its interfaces
+    let get_interface_counters = if pcap_file.is_some() || pcap_stdin {
+        get_no_interface_counters as fn() -> HashMap<String, InterfaceCounters>
+    } else {
+        get_interface_counters as fn() -> HashMap<String, InterfaceCounters>
+    };
+
+    let keyboard_events: Box<dyn Iterator<Item = Event> + Send> = if pcap_stdin {
+        // stdin is taken by the capture, so keys are read from the terminal itself
+        match get_tty() {
+            Ok(tty) => Box::new(
+                tty.events()
+                    .take_while(|event| event.is_ok())
+                    .filter_map(|event| event.ok()),
+            ),
+            Err(_) => Box::new(iter::empty()),
+        }
+    } else {
+        Box::new(KeyboardEvents)
+    };
     let write_to_stdout = create_write_to_stdout();
     let (on_winch, cleanup) = sigwinch();
     let dns_client = if resolve {
@@ -227,8 +464,14 @@ pub fn get_input(
 
     Ok(OsInputOutput {
         network_interfaces,
-        network_frames: available_network_frames,
+        network_frames,
+        capture_file,
+        socket_counters,
+        prometheus_listener: None,
+        command: None,
         get_open_sockets,
+        get_interface_counters,
+        get_capture_drops,
         keyboard_events,
         dns_client,
         on_winch,
@@ -237,6 +480,20 @@ pub fn get_input(
     })
 }
 
+// the children of processes are found from the parent of each, which only ps tells off linux
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+pub fn check_process_parents() -> Result<(), failure::Error> {
+    if let Err(e) = crate::os::lsof_utils::get_parents() {
+        failure::bail!("Cannot find the children of processes, ps failed: {}", e);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub fn check_process_parents() -> Result<(), failure::Error> {
+    Ok(())
+}
+
 #[inline]
 #[cfg(any(target_os = "macos", target_os = "freebsd"))]
 fn eperm_message() -> &'static str {
@@ -0,0 +1,418 @@
+use ::std::io;
+use ::std::mem;
+use ::std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use ::std::ptr;
+
+use crate::network::Protocol;
+
+// from linux/netlink.h, linux/sock_diag.h and linux/inet_diag.h
+const NETLINK_SOCK_DIAG: libc::c_int = 4;
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_DUMP: u16 = 0x300;
+const NLMSG_ERROR: u16 = 0x2;
+const NLMSG_DONE: u16 = 0x3;
+const ALL_STATES: u32 = !0;
+const INET_DIAG_INFO: u16 = 2;
+// offsets of tcpi_bytes_acked and tcpi_bytes_received in struct tcp_info (linux/tcp.h), which has
+// had them since linux 4.2
+const TCP_INFO_BYTES_ACKED: usize = 120;
+const TCP_INFO_BYTES_RECEIVED: usize = 128;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct NetlinkHeader {
+    length: u32,
+    message_type: u16,
+    flags: u16,
+    sequence: u32,
+    port_id: u32,
+}
+
+// ports and addresses are in network byte order, ipv4 addresses take the first 4 bytes
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct InetDiagSocketId {
+    source_port: [u8; 2],
+    destination_port: [u8; 2],
+    source: [u8; 16],
+    destination: [u8; 16],
+    interface: u32,
+    cookie: [u32; 2],
+}
+
+#[repr(C)]
+struct InetDiagRequest {
+    header: NetlinkHeader,
+    family: u8,
+    protocol: u8,
+    extensions: u8,
+    padding: u8,
+    states: u32,
+    id: InetDiagSocketId,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct InetDiagMessage {
+    family: u8,
+    state: u8,
+    timer: u8,
+    retransmits: u8,
+    id: InetDiagSocketId,
+    expires: u32,
+    receive_queue: u32,
+    write_queue: u32,
+    uid: u32,
+    inode: u32,
+}
+
+pub struct InetSocket {
+    pub local_address: SocketAddr,
+    pub remote_address: SocketAddr,
+    pub inode: u32,
+    // not known for the sockets of /proc/net
+    pub uid: Option<u32>,
+    // the bytes sent and acknowledged and the bytes received, for tcp sockets when asked for
+    pub byte_counts: Option<(u64, u64)>,
+}
+
+fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+    if result == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    let mut value = [0; 8];
+    value.copy_from_slice(bytes.get(offset..offset + 8)?);
+    Some(u64::from_ne_bytes(value))
+}
+
+// the attributes following a message, each a length and a type followed by its value, aligned to
+// 4 bytes
+fn get_byte_counts(mut attributes: &[u8]) -> Option<(u64, u64)> {
+    while attributes.len() >= 4 {
+        let length = u16::from_ne_bytes([attributes[0], attributes[1]]) as usize;
+        let attribute_type = u16::from_ne_bytes([attributes[2], attributes[3]]);
+        if length < 4 || length > attributes.len() {
+            return None;
+        }
+        if attribute_type == INET_DIAG_INFO {
+            let tcp_info = &attributes[4..length];
+            return Some((
+                read_u64(tcp_info, TCP_INFO_BYTES_ACKED)?,
+                read_u64(tcp_info, TCP_INFO_BYTES_RECEIVED)?,
+            ));
+        }
+        attributes = attributes.get((length + 3) & !3..).unwrap_or_default();
+    }
+    None
+}
+
+// the messages of one datagram of a dump, returns whether it was the last one
+fn parse_messages(datagram: &[u8], sockets: &mut Vec<InetSocket>) -> io::Result<bool> {
+    let mut offset = 0;
+    while offset + mem::size_of::<NetlinkHeader>() <= datagram.len() {
+        let header: NetlinkHeader =
+            unsafe { ptr::read_unaligned(datagram[offset..].as_ptr() as *const NetlinkHeader) };
+        let message_length = header.length as usize;
+        if message_length < mem::size_of::<NetlinkHeader>()
+            || offset + message_length > datagram.len()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "truncated netlink message",
+            ));
+        }
+        let payload = &datagram[offset + mem::size_of::<NetlinkHeader>()..offset + message_length];
+        match header.message_type {
+            NLMSG_DONE => return Ok(true),
+            NLMSG_ERROR => {
+                let error = if payload.len() >= 4 {
+                    -i32::from_ne_bytes([payload[0], payload[1], payload[2], payload[3]])
+                } else {
+                    libc::EINVAL
+                };
+                return Err(io::Error::from_raw_os_error(error));
+            }
+            _ if payload.len() >= mem::size_of::<InetDiagMessage>() => {
+                let message: InetDiagMessage =
+                    unsafe { ptr::read_unaligned(payload.as_ptr() as *const InetDiagMessage) };
+                sockets.push(InetSocket {
+                    local_address: socket_address(
+                        message.family,
+                        &message.id.source,
+                        message.id.source_port,
+                    ),
+                    remote_address: socket_address(
+                        message.family,
+                        &message.id.destination,
+                        message.id.destination_port,
+                    ),
+                    inode: message.inode,
+                    uid: Some(message.uid),
+                    byte_counts: get_byte_counts(&payload[mem::size_of::<InetDiagMessage>()..]),
+                });
+            }
+            _ => {}
+        }
+        // messages are aligned to 4 bytes
+        offset += (message_length + 3) & !3;
+    }
+    Ok(false)
+}
+
+fn socket_address(family: u8, address: &[u8; 16], port: [u8; 2]) -> SocketAddr {
+    let ip = if family == libc::AF_INET as u8 {
+        IpAddr::V4(Ipv4Addr::new(
+            address[0], address[1], address[2], address[3],
+        ))
+    } else {
+        IpAddr::V6(Ipv6Addr::from(*address))
+    };
+    SocketAddr::new(ip, u16::from_be_bytes(port))
+}
+
+// asks the kernel for its sockets over netlink, which is much cheaper than parsing the tables of
+// /proc/net and also tells the owner of each socket
+pub struct SockDiag {
+    fd: libc::c_int,
+    read_buffer: Vec<u8>,
+}
+
+impl SockDiag {
+    pub fn new() -> io::Result<Self> {
+        let fd = check(unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                NETLINK_SOCK_DIAG,
+            )
+        })?;
+        Ok(SockDiag {
+            fd,
+            read_buffer: vec![0; 32768],
+        })
+    }
+
+    pub fn get_sockets(&mut self, protocol: Protocol) -> io::Result<Vec<InetSocket>> {
+        let protocol = match protocol {
+            Protocol::Tcp => libc::IPPROTO_TCP,
+            Protocol::Udp => libc::IPPROTO_UDP,
+        } as u8;
+        let mut sockets = Vec::new();
+        for family in &[libc::AF_INET, libc::AF_INET6] {
+            self.dump(*family as u8, protocol, 0, &mut sockets)?;
+        }
+        Ok(sockets)
+    }
+
+    // the tcp sockets along with their byte counts, which needs no privileges
+    pub fn get_tcp_byte_counts(&mut self) -> io::Result<Vec<InetSocket>> {
+        let mut sockets = Vec::new();
+        for family in &[libc::AF_INET, libc::AF_INET6] {
+            self.dump(
+                *family as u8,
+                libc::IPPROTO_TCP as u8,
+                1 << (INET_DIAG_INFO - 1),
+                &mut sockets,
+            )?;
+        }
+        Ok(sockets)
+    }
+
+    fn dump(
+        &mut self,
+        family: u8,
+        protocol: u8,
+        extensions: u8,
+        sockets: &mut Vec<InetSocket>,
+    ) -> io::Result<()> {
+        let request = InetDiagRequest {
+            header: NetlinkHeader {
+                length: mem::size_of::<InetDiagRequest>() as u32,
+                message_type: SOCK_DIAG_BY_FAMILY,
+                flags: NLM_F_REQUEST | NLM_F_DUMP,
+                sequence: 0,
+                port_id: 0,
+            },
+            family,
+            protocol,
+            extensions,
+            padding: 0,
+            states: ALL_STATES,
+            id: unsafe { mem::zeroed() },
+        };
+        // an unbound netlink socket sends to the kernel
+        check(unsafe {
+            libc::send(
+                self.fd,
+                &request as *const InetDiagRequest as *const libc::c_void,
+                mem::size_of::<InetDiagRequest>(),
+                0,
+            )
+        } as libc::c_int)?;
+
+        // the dump comes in as many datagrams as needed, each holding several messages
+        loop {
+            let length = unsafe {
+                libc::recv(
+                    self.fd,
+                    self.read_buffer.as_mut_ptr() as *mut libc::c_void,
+                    self.read_buffer.len(),
+                    0,
+                )
+            };
+            if length == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            if parse_messages(&self.read_buffer[..length as usize], sockets)? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Drop for SockDiag {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute(attribute_type: u16, value: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(4 + value.len() as u16).to_ne_bytes());
+        bytes.extend_from_slice(&attribute_type.to_ne_bytes());
+        bytes.extend_from_slice(value);
+        bytes.resize((bytes.len() + 3) & !3, 0);
+        bytes
+    }
+
+    fn tcp_info(bytes_acked: u64, bytes_received: u64) -> Vec<u8> {
+        let mut tcp_info = vec![0; TCP_INFO_BYTES_RECEIVED + 8];
+        tcp_info[TCP_INFO_BYTES_ACKED..TCP_INFO_BYTES_ACKED + 8]
+            .copy_from_slice(&bytes_acked.to_ne_bytes());
+        tcp_info[TCP_INFO_BYTES_RECEIVED..TCP_INFO_BYTES_RECEIVED + 8]
+            .copy_from_slice(&bytes_received.to_ne_bytes());
+        tcp_info
+    }
+
+    // a message without its padding, which the next message (if any) comes after
+    fn message(message_type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let length = (mem::size_of::<NetlinkHeader>() + payload.len()) as u32;
+        bytes.extend_from_slice(&length.to_ne_bytes());
+        bytes.extend_from_slice(&message_type.to_ne_bytes());
+        bytes.extend_from_slice(&[0; 10]);
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    // 10.0.0.2:443 => 1.1.1.1:12345, owned by uid 1000
+    fn inet_diag_message(inode: u32, attributes: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![libc::AF_INET as u8, 1, 0, 0];
+        bytes.extend_from_slice(&443u16.to_be_bytes());
+        bytes.extend_from_slice(&12345u16.to_be_bytes());
+        let mut source = [0; 16];
+        source[..4].copy_from_slice(&[10, 0, 0, 2]);
+        let mut destination = [0; 16];
+        destination[..4].copy_from_slice(&[1, 1, 1, 1]);
+        bytes.extend_from_slice(&source);
+        bytes.extend_from_slice(&destination);
+        bytes.extend_from_slice(&[0; 12]);
+        bytes.extend_from_slice(&[0; 12]);
+        bytes.extend_from_slice(&1000u32.to_ne_bytes());
+        bytes.extend_from_slice(&inode.to_ne_bytes());
+        assert_eq!(bytes.len(), mem::size_of::<InetDiagMessage>());
+        bytes.extend_from_slice(attributes);
+        message(SOCK_DIAG_BY_FAMILY, &bytes)
+    }
+
+    fn padded(mut bytes: Vec<u8>) -> Vec<u8> {
+        bytes.resize((bytes.len() + 3) & !3, 0);
+        bytes
+    }
+
+    #[test]
+    fn test_byte_counts_after_other_attributes() {
+        let mut attributes = attribute(1, &[0; 5]);
+        attributes.extend(attribute(INET_DIAG_INFO, &tcp_info(100, 200)));
+        assert_eq!(get_byte_counts(&attributes), Some((100, 200)));
+    }
+
+    #[test]
+    fn test_byte_counts_of_short_tcp_info() {
+        let attributes = attribute(INET_DIAG_INFO, &[0; TCP_INFO_BYTES_ACKED]);
+        assert_eq!(get_byte_counts(&attributes), None);
+    }
+
+    #[test]
+    fn test_byte_counts_of_truncated_attribute() {
+        let mut attributes = attribute(INET_DIAG_INFO, &tcp_info(100, 200));
+        attributes.truncate(64);
+        assert_eq!(get_byte_counts(&attributes), None);
+        assert_eq!(get_byte_counts(&[]), None);
+    }
+
+    #[test]
+    fn test_messages_are_walked_until_done() {
+        // the odd length attribute leaves the first message unaligned
+        let mut datagram = padded(inet_diag_message(1, &attribute(1, &[0; 1])[..5]));
+        datagram.extend(padded(inet_diag_message(
+            2,
+            &attribute(INET_DIAG_INFO, &tcp_info(3, 4)),
+        )));
+        datagram.extend(message(NLMSG_DONE, &[0; 4]));
+        let mut sockets = Vec::new();
+        assert!(parse_messages(&datagram, &mut sockets).unwrap());
+        assert_eq!(sockets.len(), 2);
+        assert_eq!(sockets[0].inode, 1);
+        assert_eq!(sockets[0].local_address, "10.0.0.2:443".parse().unwrap());
+        assert_eq!(sockets[0].remote_address, "1.1.1.1:12345".parse().unwrap());
+        assert_eq!(sockets[0].uid, Some(1000));
+        assert_eq!(sockets[0].byte_counts, None);
+        assert_eq!(sockets[1].inode, 2);
+        assert_eq!(sockets[1].byte_counts, Some((3, 4)));
+    }
+
+    #[test]
+    fn test_dump_goes_on_without_done() {
+        let datagram = inet_diag_message(1, &[]);
+        let mut sockets = Vec::new();
+        assert!(!parse_messages(&datagram, &mut sockets).unwrap());
+        assert_eq!(sockets.len(), 1);
+    }
+
+    #[test]
+    fn test_truncated_message() {
+        let mut datagram = inet_diag_message(1, &[]);
+        datagram.truncate(40);
+        let error = parse_messages(&datagram, &mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut datagram = message(NLMSG_DONE, &[]);
+        datagram[..4].copy_from_slice(&8u32.to_ne_bytes());
+        let error = parse_messages(&datagram, &mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_error_message() {
+        let datagram = message(NLMSG_ERROR, &(-libc::ENOENT).to_ne_bytes());
+        let error = parse_messages(&datagram, &mut Vec::new()).unwrap_err();
+        assert_eq!(error.raw_os_error(), Some(libc::ENOENT));
+
+        let datagram = message(NLMSG_ERROR, &[]);
+        let error = parse_messages(&datagram, &mut Vec::new()).unwrap_err();
+        assert_eq!(error.raw_os_error(), Some(libc::EINVAL));
+    }
+}
@@ -0,0 +1,39 @@
+use ::insta::assert_snapshot;
+
+use crate::network::{compile_bpf, BpfInstruction, LinkType};
+
+fn listing(bpf_program: &[BpfInstruction]) -> String {
+    bpf_program
+        .iter()
+        .enumerate()
+        .map(|(index, instruction)| {
+            format!(
+                "({:03}) code 0x{:02x} jt {} jf {} k 0x{:x}",
+                index, instruction.code, instruction.jt, instruction.jf, instruction.k
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn compile_bpf_expression() {
+    let bpf_program = compile_bpf(
+        "tcp dst port 443 and not (net 10.0.0.0/8 or host ::1)",
+        LinkType::Ethernet,
+    )
+    .unwrap();
+    assert_snapshot!(listing(&bpf_program));
+}
+
+#[test]
+fn compile_bpf_expression_for_raw_ip() {
+    let bpf_program = compile_bpf("ip6 or udp src port 53", LinkType::RawIp).unwrap();
+    assert_snapshot!(listing(&bpf_program));
+}
+
+#[test]
+fn invalid_bpf_expression() {
+    let error = compile_bpf("tcp port https", LinkType::Ethernet).unwrap_err();
+    assert_eq!(error.to_string(), "\"https\" is not a port");
+}
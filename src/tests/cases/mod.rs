@@ -1,3 +1,4 @@
+pub mod bpf;
 pub mod raw_mode;
 pub mod test_utils;
 pub mod ui;
@@ -1,10 +1,15 @@
-use crate::tests::fakes::{create_fake_dns_client, NetworkFrames};
+use crate::tests::fakes::{
+    create_fake_dns_client, get_capture_drops, get_interfaces, get_no_open_sockets,
+    get_open_sockets_once, get_tcp_counters, DelayedReader, KeyboardEvents, NetworkFrames,
+};
 
 use ::insta::assert_snapshot;
 use ::std::sync::{Arc, Mutex};
 
 use ::std::collections::HashMap;
-use ::std::net::IpAddr;
+use ::std::io::{Cursor, ErrorKind, Read, Write};
+use ::std::net::{IpAddr, TcpListener, TcpStream};
+use ::std::process;
 
 use packet_builder::payload::PayloadData;
 use packet_builder::*;
@@ -12,10 +17,13 @@ use pnet_bandwhich_fork::datalink::DataLinkReceiver;
 use pnet_bandwhich_fork::packet::Packet;
 
 use crate::tests::cases::test_utils::{
-    build_tcp_packet, opts_raw, os_input_output_dns, os_input_output_stdout, test_backend_factory,
+    build_pcap_file, build_pcapng_file, build_tcp_packet, opts_raw, os_input_output_capture,
+    os_input_output_dns, os_input_output_factory, os_input_output_stdout, test_backend_factory,
 };
 
-use crate::{start, Opt, RenderOpts};
+use crate::display::SortBy;
+use crate::network::{CaptureFilter, PcapReader, PcapStream, SocketCounters};
+use crate::{start, Opt, OsInputOutput, OutputFormat, RenderOpts};
 
 fn build_ip_tcp_packet(
     source_ip: &str,
@@ -42,6 +50,14 @@ fn format_raw_output(output: Vec<u8>) -> String {
     format!("{}", replaced)
 }
 
+fn format_json_output(output: Vec<u8>) -> String {
+    let stdout_utf8 = String::from_utf8(output).unwrap();
+    use regex::Regex;
+    let timestamp = Regex::new(r#""timestamp":\d+"#).unwrap();
+    let replaced = timestamp.replace_all(&stdout_utf8, r#""timestamp":"TIMESTAMP_REMOVED""#);
+    format!("{}", replaced)
+}
+
 #[test]
 fn one_ip_packet_of_traffic() {
     let network_frames = vec![NetworkFrames::new(vec![Some(build_ip_tcp_packet(
@@ -80,6 +96,29 @@ fn one_packet_of_traffic() {
     assert_snapshot!(formatted);
 }
 
+#[test]
+fn traffic_of_closed_socket() {
+    // the packet is only counted after the socket it was sent from is gone
+    let network_frames = vec![NetworkFrames::new(vec![Some(build_tcp_packet(
+        "10.0.0.2",
+        "1.1.1.1",
+        443,
+        12345,
+        b"I am a fake tcp packet",
+    ))]) as Box<dyn DataLinkReceiver>];
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = OsInputOutput {
+        get_open_sockets: get_open_sockets_once,
+        ..os_input_output_stdout(network_frames, 2, Some(stdout.clone()))
+    };
+    let opts = opts_raw();
+    start(backend, os_input, opts);
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    let formatted = format_raw_output(stdout);
+    assert_snapshot!(formatted);
+}
+
 #[test]
 fn bi_directional_traffic() {
     let network_frames = vec![NetworkFrames::new(vec![
@@ -580,10 +619,553 @@ fn no_resolve_mode() {
             addresses: false,
             connections: false,
             processes: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    start(backend, os_input, opts);
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    let formatted = format_raw_output(stdout);
+    assert_snapshot!(formatted);
+}
+
+#[test]
+fn traffic_from_capture_file() {
+    let capture = build_pcap_file(vec![
+        (
+            1_583_000_000,
+            0,
+            build_tcp_packet(
+                "10.0.0.2",
+                "1.1.1.1",
+                443,
+                12345,
+                b"I am a fake tcp upload packet",
+            ),
+        ),
+        (
+            1_583_000_000,
+            500_000,
+            build_tcp_packet(
+                "1.1.1.1",
+                "10.0.0.2",
+                12345,
+                443,
+                b"I am a fake tcp download packet",
+            ),
+        ),
+        (
+            1_583_000_002,
+            0,
+            build_tcp_packet(
+                "2.2.2.2",
+                "10.0.0.2",
+                54321,
+                4434,
+                b"I come from 2.2.2.2, two seconds later",
+            ),
+        ),
+    ]);
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = os_input_output_capture(capture, stdout.clone());
+    let opts = opts_raw();
+    start(backend, os_input, opts);
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    // timestamps come from the capture file, so they are left in place
+    assert_snapshot!(String::from_utf8(stdout).unwrap());
+}
+
+#[test]
+fn traffic_from_pcapng_with_multiple_interfaces() {
+    let capture = build_pcapng_file(
+        &["eth0", "wlan0"],
+        vec![
+            (
+                0,
+                1_583_000_000_000_000,
+                build_tcp_packet("10.0.0.2", "1.1.1.1", 443, 12345, b"I am on eth0"),
+            ),
+            (
+                1,
+                1_583_000_000_250_000,
+                build_tcp_packet("3.3.3.3", "10.0.0.2", 1337, 4435, b"I am on wlan0"),
+            ),
+        ],
+    );
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = os_input_output_capture(capture, stdout.clone());
+    let opts = opts_raw();
+    start(backend, os_input, opts);
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    assert_snapshot!(String::from_utf8(stdout).unwrap());
+}
+
+#[test]
+fn traffic_from_pcap_stream() {
+    let capture = build_pcap_file(vec![
+        (
+            1_583_000_000,
+            0,
+            build_tcp_packet(
+                "10.0.0.2",
+                "1.1.1.1",
+                443,
+                12345,
+                b"I am a fake tcp upload packet",
+            ),
+        ),
+        (
+            1_583_000_000,
+            500_000,
+            build_tcp_packet(
+                "1.1.1.1",
+                "10.0.0.2",
+                12345,
+                443,
+                b"I am a fake tcp download packet",
+            ),
+        ),
+    ]);
+    // the stream pauses right after its header
+    let pcap_reader = PcapReader::new(DelayedReader::new(capture, 24), "stdin").unwrap();
+    let network_frames =
+        vec![Box::new(PcapStream::new(pcap_reader).unwrap()) as Box<dyn DataLinkReceiver>];
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = OsInputOutput {
+        get_open_sockets: get_no_open_sockets,
+        ..os_input_output_factory(
+            network_frames,
+            Some(stdout.clone()),
+            create_fake_dns_client(HashMap::new()),
+            Box::new(KeyboardEvents::new(Vec::new())),
+        )
+    };
+    let opts = Opt {
+        pcap_stdin: true,
+        ..opts_raw()
+    };
+    start(backend, os_input, opts);
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    let formatted = format_raw_output(stdout);
+    assert_snapshot!(formatted);
+}
+
+#[test]
+fn oversized_capture_records() {
+    let mut pcap = build_pcap_file(Vec::new());
+    pcap.extend_from_slice(&[0; 8]); // timestamp
+    pcap.extend_from_slice(&0xffff_fff0u32.to_le_bytes());
+    pcap.extend_from_slice(&0xffff_fff0u32.to_le_bytes());
+    let mut pcapng = build_pcapng_file(&["eth0"], Vec::new());
+    pcapng.extend_from_slice(&6u32.to_le_bytes()); // enhanced packet
+    pcapng.extend_from_slice(&0xffff_fff0u32.to_le_bytes());
+    for capture in [pcap, pcapng] {
+        let mut pcap_reader = PcapReader::new(Cursor::new(capture), "capture").unwrap();
+        let error = pcap_reader.next_record().err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
+
+#[test]
+fn traffic_from_socket_counters() {
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = OsInputOutput {
+        socket_counters: Some(SocketCounters::new(get_tcp_counters, get_interfaces())),
+        ..os_input_output_stdout(Vec::new(), 3, Some(stdout.clone()))
+    };
+    let opts = opts_raw();
+    start(backend, os_input, opts);
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    let formatted = format_raw_output(stdout);
+    assert_snapshot!(formatted);
+}
+
+#[test]
+fn traffic_with_capture_drops() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "1.1.1.1",
+            443,
+            12345,
+            b"I have come from 1.1.1.1",
+        )),
+        None, // sleep
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"Is it nice there? I think 1.1.1.1 is dull",
+        )),
+    ]) as Box<dyn DataLinkReceiver>];
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = OsInputOutput {
+        get_capture_drops: get_capture_drops(),
+        ..os_input_output_stdout(network_frames, 2, Some(stdout.clone()))
+    };
+    let opts = opts_raw();
+    start(backend, os_input, opts);
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    let formatted = format_raw_output(stdout);
+    assert_snapshot!(formatted);
+}
+
+#[test]
+fn json_output_format() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "3.3.3.3",
+            4435,
+            1337,
+            b"omw to 3.3.3.3",
+        )),
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"Is it nice there? I think 1.1.1.1 is dull",
+        )),
+    ]) as Box<dyn DataLinkReceiver>];
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let mut ips_to_hostnames = HashMap::new();
+    ips_to_hostnames.insert(
+        IpAddr::V4("1.1.1.1".parse().unwrap()),
+        String::from("one.one.one.one"),
+    );
+    let dns_client = create_fake_dns_client(ips_to_hostnames);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = os_input_output_dns(network_frames, 2, Some(stdout.clone()), dns_client);
+    let opts = Opt {
+        output_format: Some(OutputFormat::Json),
+        ..opts_raw()
+    };
+    start(backend, os_input, opts);
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    let formatted = format_json_output(stdout);
+    assert_snapshot!(formatted);
+}
+
+#[test]
+fn csv_output_format() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "3.3.3.3",
+            4435,
+            1337,
+            b"omw to 3.3.3.3",
+        )),
+        None, // sleep
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"Is it nice there? I think 1.1.1.1 is dull",
+        )),
+    ]) as Box<dyn DataLinkReceiver>];
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let mut ips_to_hostnames = HashMap::new();
+    ips_to_hostnames.insert(
+        IpAddr::V4("1.1.1.1".parse().unwrap()),
+        String::from("one,one \"one\" one"),
+    );
+    let dns_client = create_fake_dns_client(ips_to_hostnames);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = os_input_output_dns(network_frames, 3, Some(stdout.clone()), dns_client);
+    let opts = Opt {
+        output_format: Some(OutputFormat::Csv),
+        ..opts_raw()
+    };
+    start(backend, os_input, opts);
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    let stdout_utf8 = String::from_utf8(stdout).unwrap();
+    use regex::Regex;
+    let timestamp = Regex::new(r"(?m)^\d+,").unwrap();
+    let formatted = timestamp.replace_all(&stdout_utf8, "TIMESTAMP_REMOVED,");
+    assert_snapshot!(formatted);
+}
+
+#[test]
+fn prometheus_metrics() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        None, // sleep
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"Same here, but one second later",
+        )),
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "3.3.3.3",
+            4435,
+            1337,
+            b"omw to 3.3.3.3",
+        )),
+    ]) as Box<dyn DataLinkReceiver>];
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = OsInputOutput {
+        prometheus_listener: Some(listener),
+        command: None,
+        ..os_input_output_stdout(network_frames, 3, Some(stdout))
+    };
+    let opts = opts_raw();
+    start(backend, os_input, opts);
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert_snapshot!(response.replace("\r\n", "\n"));
+}
+
+#[test]
+fn sort_by_name() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        Some(build_tcp_packet(
+            "3.3.3.3",
+            "10.0.0.2",
+            1337,
+            4435,
+            b"Awesome, I'm from 3.3.3.3",
+        )),
+        Some(build_tcp_packet(
+            "2.2.2.2",
+            "10.0.0.2",
+            54321,
+            4434,
+            b"You know, 2.2.2.2 is really nice!",
+        )),
+    ]) as Box<dyn DataLinkReceiver>];
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let mut ips_to_hostnames = HashMap::new();
+    ips_to_hostnames.insert(
+        IpAddr::V4("2.2.2.2".parse().unwrap()),
+        String::from("alpha.example.com"),
+    );
+    let dns_client = create_fake_dns_client(ips_to_hostnames);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = os_input_output_dns(network_frames, 2, Some(stdout.clone()), dns_client);
+    let opts = Opt {
+        render_opts: RenderOpts {
+            sort: Some(SortBy::Name),
+            ..Default::default()
         },
+        ..opts_raw()
     };
     start(backend, os_input, opts);
     let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
     let formatted = format_raw_output(stdout);
     assert_snapshot!(formatted);
 }
+
+#[test]
+fn capture_filters() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        Some(build_tcp_packet(
+            "3.3.3.3",
+            "10.0.0.2",
+            1337,
+            4435,
+            b"Awesome, I'm from 3.3.3.3",
+        )),
+        Some(build_tcp_packet(
+            "2.2.2.2",
+            "10.0.0.2",
+            54321,
+            4434,
+            b"You know, 2.2.2.2 is really nice!",
+        )),
+        Some(build_tcp_packet(
+            "4.4.4.4",
+            "10.0.0.2",
+            1337,
+            4432,
+            b"Greetings from 4.4.4.4",
+        )),
+    ]) as Box<dyn DataLinkReceiver>];
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = os_input_output_stdout(network_frames, 2, Some(stdout.clone()));
+    // 4.4.4.4 is outside the networks, 2.2.2.2 is on an excluded port and 1.1.1.1 belongs to an
+    // excluded process, which leaves 3.3.3.3
+    let opts = Opt {
+        capture_filter: CaptureFilter {
+            processes: vec!["!1".parse().unwrap()],
+            ports: vec!["!4434".parse().unwrap()],
+            networks: vec!["1.0.0.0/8".parse().unwrap(), "2.0.0.0/7".parse().unwrap()],
+            ..Default::default()
+        },
+        ..opts_raw()
+    };
+    start(backend, os_input, opts);
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    let formatted = format_raw_output(stdout);
+    assert_snapshot!(formatted);
+}
+
+fn pid_filter_opts(pids: Vec<u32>, with_children: bool) -> Opt {
+    Opt {
+        capture_filter: CaptureFilter {
+            pids,
+            with_children,
+            ..Default::default()
+        },
+        ..opts_raw()
+    }
+}
+
+fn pid_filter_frames() -> Vec<Box<dyn DataLinkReceiver>> {
+    vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        Some(build_tcp_packet(
+            "3.3.3.3",
+            "10.0.0.2",
+            1337,
+            4435,
+            b"Awesome, I'm from 3.3.3.3",
+        )),
+        Some(build_tcp_packet(
+            "3.3.3.3",
+            "10.0.0.2",
+            1337,
+            4436,
+            b"And I'm from 3.3.3.3 as well",
+        )),
+    ]) as Box<dyn DataLinkReceiver>]
+}
+
+#[test]
+fn pid_filter() {
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = os_input_output_stdout(pid_filter_frames(), 2, Some(stdout.clone()));
+    // only the first of the two processes named 5
+    start(backend, os_input, pid_filter_opts(vec![1005], false));
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    let formatted = format_raw_output(stdout);
+    assert_snapshot!(formatted);
+}
+
+#[test]
+fn pid_filter_with_children() {
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let os_input = os_input_output_stdout(pid_filter_frames(), 2, Some(stdout.clone()));
+    // both processes named 5 are spawned by make
+    start(backend, os_input, pid_filter_opts(vec![1000], true));
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    let formatted = format_raw_output(stdout);
+    assert_snapshot!(formatted);
+}
+
+#[test]
+fn command_traffic_and_exit_code() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "1.1.1.1",
+            443,
+            12345,
+            b"I am a fake tcp upload packet",
+        )),
+        None, // sleep
+    ]) as Box<dyn DataLinkReceiver>];
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let command = vec![
+        String::from("sh"),
+        String::from("-c"),
+        String::from("exit 3"),
+    ];
+    // the keyboard quits well after the command is done
+    let os_input = OsInputOutput {
+        command: Some(
+            process::Command::new(&command[0])
+                .args(&command[1..])
+                .spawn()
+                .unwrap(),
+        ),
+        ..os_input_output_stdout(network_frames, 10, Some(stdout.clone()))
+    };
+    let opts = Opt {
+        command,
+        ..opts_raw()
+    };
+    let command_summary = start(backend, os_input, opts).unwrap();
+    assert_eq!(command_summary.exit_code, 3);
+    // the traffic is that of another process
+    assert_eq!(
+        command_summary.lines,
+        vec![
+            "sh -c exit 3 exited with code 3",
+            "Total Up / Down: 0B / 0B"
+        ]
+    );
+    let stdout = Arc::try_unwrap(stdout).unwrap().into_inner().unwrap();
+    assert!(!format_raw_output(stdout).contains("process:"));
+}
+
+#[test]
+fn command_stopped_on_quit() {
+    let network_frames = vec![NetworkFrames::new(vec![None]) as Box<dyn DataLinkReceiver>];
+    let (_, _, backend) = test_backend_factory(190, 50);
+    let command = vec![String::from("sleep"), String::from("60")];
+    let os_input = OsInputOutput {
+        command: Some(
+            process::Command::new(&command[0])
+                .args(&command[1..])
+                .spawn()
+                .unwrap(),
+        ),
+        ..os_input_output_stdout(network_frames, 2, None)
+    };
+    let opts = Opt {
+        command,
+        ..opts_raw()
+    };
+    let command_summary = start(backend, os_input, opts).unwrap();
+    assert_eq!(command_summary.exit_code, 128 + 9);
+    assert_eq!(command_summary.lines[0], "sleep 60 was killed by signal 9");
+}
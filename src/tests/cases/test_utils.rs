@@ -1,16 +1,17 @@
 use crate::tests::fakes::{
-    create_fake_dns_client, create_fake_on_winch, get_interfaces, get_open_sockets, KeyboardEvents,
-    NetworkFrames, TerminalEvent, TestBackend,
+    create_fake_dns_client, create_fake_on_winch, get_interfaces, get_no_interface_counters,
+    get_no_open_sockets, get_open_sockets, KeyboardEvents, NetworkFrames, TerminalEvent,
+    TestBackend,
 };
 use std::iter;
 
-use crate::network::dns::Client;
+use crate::network::{dns::Client, PcapReader};
 use crate::{Opt, OsInputOutput, RenderOpts};
 use ::termion::event::{Event, Key};
 use packet_builder::*;
 use pnet_bandwhich_fork::datalink::DataLinkReceiver;
 use std::collections::HashMap;
-use std::io::Write;
+use std::io::{Cursor, Read, Write};
 use std::sync::{Arc, Mutex};
 
 use packet_builder::payload::PayloadData;
@@ -60,6 +61,94 @@ pub fn sample_frames() -> Vec<Box<dyn DataLinkReceiver>> {
     ]) as Box<dyn DataLinkReceiver>]
 }
 
+pub fn build_pcap_file(packets: Vec<(u32, u32, Vec<u8>)>) -> Vec<u8> {
+    let mut pcap = Vec::new();
+    pcap.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+    pcap.extend_from_slice(&2u16.to_le_bytes());
+    pcap.extend_from_slice(&4u16.to_le_bytes());
+    pcap.extend_from_slice(&[0; 8]); // thiszone and sigfigs
+    pcap.extend_from_slice(&65535u32.to_le_bytes());
+    pcap.extend_from_slice(&1u32.to_le_bytes()); // ethernet
+    for (seconds, micros, packet) in packets {
+        pcap.extend_from_slice(&seconds.to_le_bytes());
+        pcap.extend_from_slice(&micros.to_le_bytes());
+        pcap.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        pcap.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        pcap.extend_from_slice(&packet);
+    }
+    pcap
+}
+
+fn pcapng_block(block_type: u32, mut body: Vec<u8>) -> Vec<u8> {
+    while body.len() % 4 != 0 {
+        body.push(0);
+    }
+    let block_length = (body.len() + 12) as u32;
+    let mut block = Vec::new();
+    block.extend_from_slice(&block_type.to_le_bytes());
+    block.extend_from_slice(&block_length.to_le_bytes());
+    block.extend_from_slice(&body);
+    block.extend_from_slice(&block_length.to_le_bytes());
+    block
+}
+
+pub fn build_pcapng_file(interface_names: &[&str], packets: Vec<(u32, u64, Vec<u8>)>) -> Vec<u8> {
+    let mut section_header = Vec::new();
+    section_header.extend_from_slice(&0x1a2b_3c4du32.to_le_bytes());
+    section_header.extend_from_slice(&1u16.to_le_bytes());
+    section_header.extend_from_slice(&0u16.to_le_bytes());
+    section_header.extend_from_slice(&(-1i64).to_le_bytes());
+    let mut pcapng = pcapng_block(0x0a0d_0d0a, section_header);
+    for interface_name in interface_names {
+        let mut interface_description = Vec::new();
+        interface_description.extend_from_slice(&1u16.to_le_bytes()); // ethernet
+        interface_description.extend_from_slice(&[0; 2]);
+        interface_description.extend_from_slice(&65535u32.to_le_bytes());
+        interface_description.extend_from_slice(&2u16.to_le_bytes()); // if_name
+        interface_description.extend_from_slice(&(interface_name.len() as u16).to_le_bytes());
+        interface_description.extend_from_slice(interface_name.as_bytes());
+        while interface_description.len() % 4 != 0 {
+            interface_description.push(0);
+        }
+        interface_description.extend_from_slice(&[0; 4]); // opt_endofopt
+        pcapng.extend(pcapng_block(1, interface_description));
+    }
+    for (interface_id, micros, packet) in packets {
+        let mut enhanced_packet = Vec::new();
+        enhanced_packet.extend_from_slice(&interface_id.to_le_bytes());
+        enhanced_packet.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+        enhanced_packet.extend_from_slice(&(micros as u32).to_le_bytes());
+        enhanced_packet.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        enhanced_packet.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        enhanced_packet.extend_from_slice(&packet);
+        pcapng.extend(pcapng_block(6, enhanced_packet));
+    }
+    pcapng
+}
+
+pub fn os_input_output_capture(capture: Vec<u8>, stdout: Arc<Mutex<Vec<u8>>>) -> OsInputOutput {
+    let capture_file = PcapReader::new(
+        Box::new(Cursor::new(capture)) as Box<dyn Read + Send>,
+        "capture",
+    )
+    .unwrap()
+    .with_local_ips(vec!["10.0.0.2".parse().unwrap()]);
+    OsInputOutput {
+        network_interfaces: Vec::new(),
+        capture_file: Some(capture_file),
+        socket_counters: None,
+        get_open_sockets: get_no_open_sockets,
+        get_interface_counters: get_no_interface_counters,
+        get_capture_drops: Box::new(HashMap::new),
+        ..os_input_output_factory(
+            Vec::new(),
+            Some(stdout),
+            create_fake_dns_client(HashMap::new()),
+            Box::new(KeyboardEvents::new(Vec::new())),
+        )
+    }
+}
+
 pub fn os_input_output(
     network_frames: Vec<Box<dyn DataLinkReceiver>>,
     sleep_num: usize,
@@ -120,6 +209,12 @@ pub fn os_input_output_factory(
     OsInputOutput {
         network_interfaces: get_interfaces(),
         network_frames,
+        capture_file: None,
+        socket_counters: None,
+        get_interface_counters: get_no_interface_counters,
+        get_capture_drops: Box::new(HashMap::new),
+        prometheus_listener: None,
+        command: None,
         get_open_sockets,
         keyboard_events,
         dns_client,
@@ -146,7 +241,9 @@ fn opts_factory(raw: bool) -> Opt {
             addresses: false,
             connections: false,
             processes: false,
+            ..Default::default()
         },
+        ..Default::default()
     }
 }
 type BackendWithStreams = (
@@ -1,9 +1,12 @@
 use crate::tests::fakes::TerminalEvent::*;
 use crate::tests::fakes::{
-    create_fake_dns_client, create_fake_on_winch, get_interfaces, get_open_sockets, NetworkFrames,
+    create_fake_dns_client, create_fake_on_winch, get_capture_drops, get_interface_counters,
+    get_interfaces, get_no_interface_counters, get_no_open_sockets, get_open_sockets,
+    get_open_sockets_in_containers, get_tcp_counters, NetworkFrames,
 };
 
 use ::insta::assert_snapshot;
+use ::regex::Regex;
 
 use ::std::collections::HashMap;
 use ::std::net::IpAddr;
@@ -18,6 +21,7 @@ use std::iter;
 
 use crate::tests::fakes::KeyboardEvents;
 
+use crate::network::SocketCounters;
 use crate::{start, Opt, OsInputOutput, RenderOpts};
 
 #[test]
@@ -107,7 +111,9 @@ fn basic_only_processes() {
             addresses: false,
             connections: false,
             processes: true,
+            ..Default::default()
         },
+        ..Default::default()
     };
 
     start(backend, os_input, opts);
@@ -131,7 +137,9 @@ fn basic_only_connections() {
             addresses: false,
             connections: true,
             processes: false,
+            ..Default::default()
         },
+        ..Default::default()
     };
 
     start(backend, os_input, opts);
@@ -155,7 +163,9 @@ fn basic_only_addresses() {
             addresses: true,
             connections: false,
             processes: false,
+            ..Default::default()
         },
+        ..Default::default()
     };
 
     start(backend, os_input, opts);
@@ -177,7 +187,9 @@ fn two_packets_only_processes() {
             addresses: false,
             connections: false,
             processes: true,
+            ..Default::default()
         },
+        ..Default::default()
     };
 
     start(backend, os_input, opts);
@@ -200,7 +212,9 @@ fn two_packets_only_connections() {
             addresses: false,
             connections: true,
             processes: false,
+            ..Default::default()
         },
+        ..Default::default()
     };
 
     start(backend, os_input, opts);
@@ -223,7 +237,9 @@ fn two_packets_only_addresses() {
             addresses: true,
             connections: false,
             processes: false,
+            ..Default::default()
         },
+        ..Default::default()
     };
 
     start(backend, os_input, opts);
@@ -232,6 +248,244 @@ fn two_packets_only_addresses() {
     assert_snapshot!(&terminal_draw_events_mirror[1]);
 }
 
+#[test]
+fn two_packets_only_users() {
+    let network_frames = sample_frames();
+
+    let (_, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let os_input = os_input_output(network_frames, 2);
+    let opts = Opt {
+        interface: Some(String::from("interface_name")),
+        raw: false,
+        no_resolve: false,
+        render_opts: RenderOpts {
+            users: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    assert_snapshot!(&terminal_draw_events_mirror[0]);
+    assert_snapshot!(&terminal_draw_events_mirror[1]);
+}
+
+#[test]
+fn two_packets_five_tables() {
+    let network_frames = sample_frames();
+
+    let (_, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let os_input = os_input_output(network_frames, 2);
+    let opts = Opt {
+        interface: Some(String::from("interface_name")),
+        raw: false,
+        no_resolve: false,
+        render_opts: RenderOpts {
+            processes: true,
+            addresses: true,
+            connections: true,
+            users: true,
+            units: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    assert_snapshot!(&terminal_draw_events_mirror[0]);
+}
+
+#[test]
+fn multiple_processes_in_containers() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        Some(build_tcp_packet(
+            "3.3.3.3",
+            "10.0.0.2",
+            1337,
+            4435,
+            b"Awesome, I'm from 3.3.3.3",
+        )),
+        Some(build_tcp_packet(
+            "2.2.2.2",
+            "10.0.0.2",
+            54321,
+            4434,
+            b"You know, 2.2.2.2 is really nice!",
+        )),
+    ]) as Box<dyn DataLinkReceiver>];
+
+    let (_, terminal_draw_events, backend) = test_backend_factory(220, 50);
+    let os_input = OsInputOutput {
+        get_open_sockets: get_open_sockets_in_containers,
+        ..os_input_output(network_frames, 2)
+    };
+    let opts = Opt {
+        interface: Some(String::from("interface_name")),
+        raw: false,
+        no_resolve: false,
+        render_opts: RenderOpts {
+            connections: true,
+            containers: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    assert_snapshot!(&terminal_draw_events_mirror[0]);
+    assert_snapshot!(&terminal_draw_events_mirror[1]);
+}
+
+#[test]
+fn multiple_processes_by_unit() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        Some(build_tcp_packet(
+            "3.3.3.3",
+            "10.0.0.2",
+            1337,
+            4435,
+            b"Awesome, I'm from 3.3.3.3",
+        )),
+        Some(build_tcp_packet(
+            "3.3.3.3",
+            "10.0.0.2",
+            1337,
+            4436,
+            b"And I'm from 3.3.3.3 as well",
+        )),
+    ]) as Box<dyn DataLinkReceiver>];
+
+    let (_, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let os_input = os_input_output(network_frames, 2);
+    let opts = Opt {
+        interface: Some(String::from("interface_name")),
+        raw: false,
+        no_resolve: false,
+        render_opts: RenderOpts {
+            units: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    assert_snapshot!(&terminal_draw_events_mirror[0]);
+    assert_snapshot!(&terminal_draw_events_mirror[1]);
+}
+
+#[test]
+fn traffic_with_capture_drops() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        None, // sleep
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "1.1.1.1",
+            443,
+            12345,
+            b"Back to 1.1.1.1",
+        )),
+    ]) as Box<dyn DataLinkReceiver>];
+
+    let (_, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let os_input = OsInputOutput {
+        get_capture_drops: get_capture_drops(),
+        ..os_input_output(network_frames, 2)
+    };
+    let opts = Opt {
+        interface: Some(String::from("interface_name")),
+        raw: false,
+        no_resolve: false,
+        ..Default::default()
+    };
+
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    assert_snapshot!(&terminal_draw_events_mirror[0]);
+    assert_snapshot!(&terminal_draw_events_mirror[1]);
+}
+
+#[test]
+fn traffic_from_socket_counters() {
+    let (_, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let os_input = OsInputOutput {
+        socket_counters: Some(SocketCounters::new(get_tcp_counters, get_interfaces())),
+        ..os_input_output(Vec::new(), 2)
+    };
+    let opts = opts_ui();
+
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    assert_snapshot!(&terminal_draw_events_mirror[0]);
+}
+
+#[test]
+fn traffic_by_interface() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        None, // sleep
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "1.1.1.1",
+            443,
+            12345,
+            b"Back to 1.1.1.1",
+        )),
+    ]) as Box<dyn DataLinkReceiver>];
+
+    let (_, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let os_input = OsInputOutput {
+        get_interface_counters,
+        ..os_input_output(network_frames, 3)
+    };
+    let opts = Opt {
+        interface: Some(String::from("interface_name")),
+        raw: false,
+        no_resolve: false,
+        render_opts: RenderOpts {
+            interfaces: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    assert_snapshot!(&terminal_draw_events_mirror[0]);
+    assert_snapshot!(&terminal_draw_events_mirror[1]);
+    assert_snapshot!(&terminal_draw_events_mirror[2]);
+}
+
 #[test]
 fn two_windows_split_horizontally() {
     let network_frames = vec![NetworkFrames::new(vec![
@@ -248,7 +502,9 @@ fn two_windows_split_horizontally() {
             addresses: true,
             connections: true,
             processes: false,
+            ..Default::default()
         },
+        ..Default::default()
     };
 
     start(backend, os_input, opts);
@@ -272,7 +528,9 @@ fn two_windows_split_vertically() {
             addresses: true,
             connections: true,
             processes: false,
+            ..Default::default()
         },
+        ..Default::default()
     };
 
     start(backend, os_input, opts);
@@ -796,6 +1054,12 @@ fn traffic_with_host_names() {
     let os_input = OsInputOutput {
         network_interfaces: get_interfaces(),
         network_frames,
+        capture_file: None,
+        socket_counters: None,
+        get_interface_counters: get_no_interface_counters,
+        get_capture_drops: Box::new(HashMap::new),
+        prometheus_listener: None,
+        command: None,
         get_open_sockets,
         keyboard_events: sleep_and_quit_events(3),
         dns_client,
@@ -905,6 +1169,12 @@ fn truncate_long_hostnames() {
     let os_input = OsInputOutput {
         network_interfaces: get_interfaces(),
         network_frames,
+        capture_file: None,
+        socket_counters: None,
+        get_interface_counters: get_no_interface_counters,
+        get_capture_drops: Box::new(HashMap::new),
+        prometheus_listener: None,
+        command: None,
         get_open_sockets,
         keyboard_events: sleep_and_quit_events(3),
         dns_client,
@@ -1013,6 +1283,12 @@ fn no_resolve_mode() {
     let os_input = OsInputOutput {
         network_interfaces: get_interfaces(),
         network_frames,
+        capture_file: None,
+        socket_counters: None,
+        get_interface_counters: get_no_interface_counters,
+        get_capture_drops: Box::new(HashMap::new),
+        prometheus_listener: None,
+        command: None,
         get_open_sockets,
         keyboard_events: sleep_and_quit_events(3),
         dns_client,
@@ -1057,6 +1333,12 @@ fn traffic_with_winch_event() {
     let os_input = OsInputOutput {
         network_interfaces: get_interfaces(),
         network_frames,
+        capture_file: None,
+        socket_counters: None,
+        get_interface_counters: get_no_interface_counters,
+        get_capture_drops: Box::new(HashMap::new),
+        prometheus_listener: None,
+        command: None,
         get_open_sockets,
         keyboard_events: sleep_and_quit_events(2),
         dns_client,
@@ -1237,3 +1519,485 @@ fn layout_under_120_width_under_30_height() {
     assert_snapshot!(&terminal_draw_events_mirror[0]);
     assert_snapshot!(&terminal_draw_events_mirror[1]);
 }
+
+#[test]
+fn no_process_information() {
+    let network_frames = vec![NetworkFrames::new(vec![Some(build_tcp_packet(
+        "1.1.1.1",
+        "10.0.0.2",
+        12345,
+        443,
+        b"I have come from 1.1.1.1",
+    ))]) as Box<dyn DataLinkReceiver>];
+    let (_, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let os_input = OsInputOutput {
+        get_open_sockets: get_no_open_sockets,
+        ..os_input_output(network_frames, 2)
+    };
+    let opts = Opt {
+        pcap_stdin: true,
+        ..opts_ui()
+    };
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+
+    assert_eq!(terminal_draw_events_mirror.len(), 2);
+    assert_snapshot!(&terminal_draw_events_mirror[0]);
+    assert_snapshot!(&terminal_draw_events_mirror[1]);
+}
+
+#[test]
+fn toggle_totals() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        None, // sleep
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"Same here, but one second later",
+        )),
+        None, // sleep
+        None, // sleep
+    ]) as Box<dyn DataLinkReceiver>];
+
+    // sleep for 2s, then press t, sleep for 1s, then quit
+    let mut events: Vec<Option<Event>> = iter::repeat(None).take(2).collect();
+    events.push(Some(Event::Key(Key::Char('t'))));
+    events.push(None);
+    events.push(Some(Event::Key(Key::Ctrl('c'))));
+
+    let events = Box::new(KeyboardEvents::new(events));
+    let os_input = os_input_output_factory(network_frames, None, None, events);
+    let (terminal_events, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let opts = opts_ui();
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    let expected_terminal_events = vec![
+        Clear, HideCursor, Draw, Flush, Draw, Flush, Draw, Flush, Draw, Flush, Clear, ShowCursor,
+    ];
+    assert_eq!(
+        &terminal_events.lock().unwrap()[..],
+        &expected_terminal_events[..]
+    );
+    assert_eq!(terminal_draw_events_mirror.len(), 4);
+    assert_snapshot!(&terminal_draw_events_mirror[2]);
+    assert_snapshot!(&terminal_draw_events_mirror[3]);
+}
+
+#[test]
+fn totals_count_while_paused() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        None, // sleep
+        None, // sleep
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"Same here, while paused",
+        )),
+        None, // sleep
+        None, // sleep
+    ]) as Box<dyn DataLinkReceiver>];
+
+    // sleep for 1s, then press space, sleep for 3s, then press t and quit
+    let mut events: Vec<Option<Event>> = iter::repeat(None).take(1).collect();
+    events.push(Some(Event::Key(Key::Char(' '))));
+    events.push(None);
+    events.push(None);
+    events.push(None);
+    events.push(Some(Event::Key(Key::Char('t'))));
+    events.push(Some(Event::Key(Key::Ctrl('c'))));
+
+    let events = Box::new(KeyboardEvents::new(events));
+    let os_input = os_input_output_factory(network_frames, None, None, events);
+    let (_, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let opts = opts_ui();
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    assert_snapshot!(terminal_draw_events_mirror.last().unwrap());
+}
+
+#[test]
+fn sort_by_key() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "3.3.3.3",
+            4435,
+            1337,
+            b"omw to 3.3.3.3",
+        )),
+        None, // sleep
+        None, // sleep
+    ]) as Box<dyn DataLinkReceiver>];
+
+    // sleep for 1s, then press s (sort by upload), sleep for 1s, then quit
+    let mut events: Vec<Option<Event>> = iter::repeat(None).take(1).collect();
+    events.push(Some(Event::Key(Key::Char('s'))));
+    events.push(None);
+    events.push(Some(Event::Key(Key::Ctrl('c'))));
+
+    let events = Box::new(KeyboardEvents::new(events));
+    let os_input = os_input_output_factory(network_frames, None, None, events);
+    let (terminal_events, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let opts = opts_ui();
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    let expected_terminal_events = vec![
+        Clear, HideCursor, Draw, Flush, Draw, Flush, Draw, Flush, Clear, ShowCursor,
+    ];
+    assert_eq!(
+        &terminal_events.lock().unwrap()[..],
+        &expected_terminal_events[..]
+    );
+    assert_eq!(terminal_draw_events_mirror.len(), 3);
+    assert_snapshot!(&terminal_draw_events_mirror[0]);
+    assert_snapshot!(&terminal_draw_events_mirror[1]);
+}
+
+#[test]
+fn sort_by_name_key() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "3.3.3.3",
+            4435,
+            1337,
+            b"omw to 3.3.3.3",
+        )),
+        None, // sleep
+        None, // sleep
+    ]) as Box<dyn DataLinkReceiver>];
+
+    // sleep for 1s, then press n (sort by name), sleep for 1s, then quit
+    let mut events: Vec<Option<Event>> = iter::repeat(None).take(1).collect();
+    events.push(Some(Event::Key(Key::Char('n'))));
+    events.push(None);
+    events.push(Some(Event::Key(Key::Ctrl('c'))));
+
+    let events = Box::new(KeyboardEvents::new(events));
+    let os_input = os_input_output_factory(network_frames, None, None, events);
+    let (terminal_events, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let opts = opts_ui();
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    let expected_terminal_events = vec![
+        Clear, HideCursor, Draw, Flush, Draw, Flush, Draw, Flush, Clear, ShowCursor,
+    ];
+    assert_eq!(
+        &terminal_events.lock().unwrap()[..],
+        &expected_terminal_events[..]
+    );
+    assert_eq!(terminal_draw_events_mirror.len(), 3);
+    assert_snapshot!(&terminal_draw_events_mirror[0]);
+    assert_snapshot!(&terminal_draw_events_mirror[1]);
+}
+
+#[test]
+fn scroll_connections_table() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"xxxxxxxxxx",
+        )),
+        Some(build_tcp_packet(
+            "2.2.2.2",
+            "10.0.0.2",
+            12345,
+            443,
+            b"xxxxxxxxxxxxxxxxxxxx",
+        )),
+        Some(build_tcp_packet(
+            "3.3.3.3",
+            "10.0.0.2",
+            12345,
+            443,
+            b"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxx",
+        )),
+        Some(build_tcp_packet(
+            "4.4.4.4",
+            "10.0.0.2",
+            12345,
+            443,
+            b"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx",
+        )),
+        Some(build_tcp_packet(
+            "5.5.5.5",
+            "10.0.0.2",
+            12345,
+            443,
+            b"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx",
+        )),
+        Some(build_tcp_packet(
+            "6.6.6.6",
+            "10.0.0.2",
+            12345,
+            443,
+            b"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx",
+        )),
+        Some(build_tcp_packet(
+            "7.7.7.7",
+            "10.0.0.2",
+            12345,
+            443,
+            b"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx",
+        )),
+        None, // sleep
+        None, // sleep
+    ]) as Box<dyn DataLinkReceiver>];
+
+    // sleep for 2s, then focus the table and move the selection past its last visible row
+    let mut events: Vec<Option<Event>> = iter::repeat(None).take(2).collect();
+    events.push(Some(Event::Key(Key::Char('\t'))));
+    events.extend(iter::repeat(Some(Event::Key(Key::Down))).take(5));
+    events.push(None);
+    events.push(Some(Event::Key(Key::Ctrl('c'))));
+
+    let events = Box::new(KeyboardEvents::new(events));
+    let os_input = os_input_output_factory(network_frames, None, None, events);
+    let (terminal_events, terminal_draw_events, backend) = test_backend_factory(190, 10);
+    let opts = Opt {
+        render_opts: RenderOpts {
+            connections: true,
+            ..Default::default()
+        },
+        ..opts_ui()
+    };
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    let expected_terminal_events = vec![
+        Clear, HideCursor, Draw, Flush, Draw, Flush, Draw, Flush, Draw, Flush, Draw, Flush, Draw,
+        Flush, Draw, Flush, Draw, Flush, Draw, Flush, Clear, ShowCursor,
+    ];
+    assert_eq!(
+        &terminal_events.lock().unwrap()[..],
+        &expected_terminal_events[..]
+    );
+    assert_eq!(terminal_draw_events_mirror.len(), 9);
+    assert_snapshot!(&terminal_draw_events_mirror[1]);
+    assert_snapshot!(&terminal_draw_events_mirror[7]);
+}
+
+#[test]
+fn connection_detail_pane() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I am a fake tcp upload packet",
+        )),
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "1.1.1.1",
+            443,
+            12345,
+            b"I am a fake tcp download packet",
+        )),
+        None, // sleep
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "1.1.1.1",
+            443,
+            12345,
+            b"I am a fake tcp download packet",
+        )),
+    ]) as Box<dyn DataLinkReceiver>];
+
+    // sleep for 2s, then select the connection and open its details
+    let mut events: Vec<Option<Event>> = iter::repeat(None).take(2).collect();
+    events.push(Some(Event::Key(Key::Char('\t'))));
+    events.push(Some(Event::Key(Key::Char('\n'))));
+    events.push(None);
+    events.push(Some(Event::Key(Key::Ctrl('c'))));
+
+    let events = Box::new(KeyboardEvents::new(events));
+    let os_input = os_input_output_factory(network_frames, None, None, events);
+    let (_, terminal_draw_events, backend) = test_backend_factory(190, 30);
+    let opts = Opt {
+        render_opts: RenderOpts {
+            connections: true,
+            ..Default::default()
+        },
+        ..opts_ui()
+    };
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    // the first seen time depends on the local time zone
+    let first_seen = Regex::new(r"\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}").unwrap();
+    let detail_pane = terminal_draw_events_mirror
+        .iter()
+        .find(|draw| draw.contains("Connection details"))
+        .expect("the detail pane was not drawn");
+    assert_snapshot!(first_seen.replace_all(detail_pane, "<first seen>"));
+}
+
+#[test]
+fn filter_by_prompt() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "1.1.1.1",
+            "10.0.0.2",
+            12345,
+            443,
+            b"I have come from 1.1.1.1",
+        )),
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "3.3.3.3",
+            4435,
+            1337,
+            b"omw to 3.3.3.3",
+        )),
+        None, // sleep
+        None, // sleep
+    ]) as Box<dyn DataLinkReceiver>];
+
+    // sleep for 2s, then type in a filter (q is part of it rather than quitting) and apply it
+    // to the totals, sleep for 1s, then quit
+    let mut events: Vec<Option<Event>> = iter::repeat(None).take(2).collect();
+    events.extend(
+        "/q|3.3\n"
+            .chars()
+            .map(|character| Some(Event::Key(Key::Char(character)))),
+    );
+    events.push(Some(Event::Key(Key::Char('f'))));
+    events.push(None);
+    events.push(Some(Event::Key(Key::Ctrl('c'))));
+
+    let events = Box::new(KeyboardEvents::new(events));
+    let os_input = os_input_output_factory(network_frames, None, None, events);
+    let (_, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let opts = opts_ui();
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    assert_eq!(terminal_draw_events_mirror.len(), 11);
+    // the filter while it is typed in, once it is applied, and once it applies to the totals
+    assert_snapshot!(&terminal_draw_events_mirror[7]);
+    assert_snapshot!(&terminal_draw_events_mirror[8]);
+    assert_snapshot!(&terminal_draw_events_mirror[9]);
+}
+
+#[test]
+fn group_processes_by_name() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "3.3.3.3",
+            4435,
+            1337,
+            b"omw to 3.3.3.3",
+        )),
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "3.3.3.3",
+            4436,
+            1337,
+            b"me too, from another process",
+        )),
+        None, // sleep
+        None, // sleep
+    ]) as Box<dyn DataLinkReceiver>];
+
+    // sleep for 2s, then press g, sleep for 1s, then quit
+    let mut events: Vec<Option<Event>> = iter::repeat(None).take(2).collect();
+    events.push(Some(Event::Key(Key::Char('g'))));
+    events.push(None);
+    events.push(Some(Event::Key(Key::Ctrl('c'))));
+
+    let events = Box::new(KeyboardEvents::new(events));
+    let os_input = os_input_output_factory(network_frames, None, None, events);
+    let (_, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let opts = opts_ui();
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    assert_eq!(terminal_draw_events_mirror.len(), 4);
+    // both processes of the same name, then a row for their name
+    assert_snapshot!(&terminal_draw_events_mirror[1]);
+    assert_snapshot!(&terminal_draw_events_mirror[2]);
+}
+
+#[test]
+fn process_tree() {
+    let network_frames = vec![NetworkFrames::new(vec![
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "1.1.1.1",
+            443,
+            12345,
+            b"I am a fake tcp upload packet",
+        )),
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "3.3.3.3",
+            4435,
+            1337,
+            b"omw to 3.3.3.3",
+        )),
+        Some(build_tcp_packet(
+            "10.0.0.2",
+            "3.3.3.3",
+            4436,
+            1337,
+            b"me too, from another process",
+        )),
+        None, // sleep
+        None, // sleep
+    ]) as Box<dyn DataLinkReceiver>];
+
+    // sleep for 2s, press p, sleep for 1s, then select the third row of the tree and collapse it
+    let mut events: Vec<Option<Event>> = iter::repeat(None).take(2).collect();
+    events.push(Some(Event::Key(Key::Char('p'))));
+    events.push(None);
+    events.push(Some(Event::Key(Key::Char('\t'))));
+    events.push(Some(Event::Key(Key::Down)));
+    events.push(Some(Event::Key(Key::Down)));
+    events.push(Some(Event::Key(Key::Left)));
+    events.push(Some(Event::Key(Key::Ctrl('c'))));
+
+    let events = Box::new(KeyboardEvents::new(events));
+    let os_input = os_input_output_factory(network_frames, None, None, events);
+    let (_, terminal_draw_events, backend) = test_backend_factory(190, 50);
+    let opts = opts_ui();
+    start(backend, os_input, opts);
+    let terminal_draw_events_mirror = terminal_draw_events.lock().unwrap();
+    assert_eq!(terminal_draw_events_mirror.len(), 8);
+    // the tree with the traffic of both processes named 5 rolled up into their build, then the
+    // build collapsed
+    assert_snapshot!(&terminal_draw_events_mirror[2]);
+    assert_snapshot!(&terminal_draw_events_mirror[7]);
+}
@@ -2,8 +2,10 @@ use ::async_trait::async_trait;
 use ::ipnetwork::IpNetwork;
 use ::pnet_bandwhich_fork::datalink::DataLinkReceiver;
 use ::pnet_bandwhich_fork::datalink::NetworkInterface;
-use ::std::collections::HashMap;
+use ::std::collections::{HashMap, HashSet};
+use ::std::io::{self, Cursor, Read};
 use ::std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use ::std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use ::std::{thread, time};
 use ::termion::event::Event;
 use ::tokio::runtime::Runtime;
@@ -11,10 +13,10 @@ use ::tokio::runtime::Runtime;
 use crate::{
     network::{
         dns::{self, Lookup},
-        Connection, Protocol,
+        Connection, Protocol, SocketCounter,
     },
     os::OnSigWinch,
-    OpenSockets,
+    Container, GetCaptureDrops, InterfaceCounters, OpenSockets, ProcessInfo,
 };
 
 pub struct KeyboardEvents {
@@ -84,6 +86,28 @@ impl DataLinkReceiver for NetworkFrames {
     }
 }
 
+fn process(name: &str, pid: u32, user: &str) -> ProcessInfo {
+    // the processes named 5 are spawned by the same build
+    let ancestors = match name {
+        "5" => vec![(1000, "make"), (900, "bash"), (1, "init")],
+        _ => vec![(1, "init")],
+    };
+    ProcessInfo {
+        pid,
+        name: name.to_string(),
+        ancestors: ancestors
+            .into_iter()
+            .map(|(pid, name)| (pid, name.to_string()))
+            .collect(),
+        cmdline: format!("/usr/bin/{} --fake", name),
+        exe: format!("/usr/bin/{}", name),
+        user: user.to_string(),
+        cgroup: format!("/system.slice/{}.service", name),
+        unit: format!("{}.service", name),
+        container: None,
+    }
+}
+
 pub fn get_open_sockets() -> OpenSockets {
     let mut open_sockets = HashMap::new();
     let local_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
@@ -94,7 +118,7 @@ pub fn get_open_sockets() -> OpenSockets {
             443,
             Protocol::Tcp,
         ),
-        String::from("1"),
+        process("1", 1001, "alice"),
     );
     open_sockets.insert(
         Connection::new(
@@ -103,7 +127,7 @@ pub fn get_open_sockets() -> OpenSockets {
             4434,
             Protocol::Tcp,
         ),
-        String::from("4"),
+        process("4", 1004, "alice"),
     );
     open_sockets.insert(
         Connection::new(
@@ -112,7 +136,7 @@ pub fn get_open_sockets() -> OpenSockets {
             4435,
             Protocol::Tcp,
         ),
-        String::from("5"),
+        process("5", 1005, "bob"),
     );
     open_sockets.insert(
         Connection::new(
@@ -121,7 +145,7 @@ pub fn get_open_sockets() -> OpenSockets {
             4432,
             Protocol::Tcp,
         ),
-        String::from("2"),
+        process("2", 1002, "alice"),
     );
     open_sockets.insert(
         Connection::new(
@@ -130,18 +154,181 @@ pub fn get_open_sockets() -> OpenSockets {
             443,
             Protocol::Tcp,
         ),
-        String::from("1"),
+        process("1", 1001, "alice"),
+    );
+    // a second process of the same name
+    open_sockets.insert(
+        Connection::new(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(3, 3, 3, 3)), 1337),
+            local_ip,
+            4436,
+            Protocol::Tcp,
+        ),
+        process("5", 1006, "bob"),
     );
     let mut local_socket_to_procs = HashMap::new();
     let mut connections = std::vec::Vec::new();
-    for (connection, process_name) in open_sockets {
-        local_socket_to_procs.insert(connection.local_socket, process_name);
+    for (connection, process_info) in open_sockets {
+        local_socket_to_procs.insert(connection.local_socket, process_info);
         connections.push(connection);
     }
 
+    let pids = local_socket_to_procs
+        .values()
+        .flat_map(|process_info| {
+            process_info
+                .ancestors
+                .iter()
+                .map(|(pid, _)| *pid)
+                .chain(Some(process_info.pid))
+        })
+        .collect();
     OpenSockets {
         sockets_to_procs: local_socket_to_procs,
         connections,
+        pids,
+    }
+}
+
+// the same sockets, with the processes named 4 and 5 in docker and kubernetes containers
+pub fn get_open_sockets_in_containers() -> OpenSockets {
+    let mut open_sockets = get_open_sockets();
+    for process_info in open_sockets.sockets_to_procs.values_mut() {
+        let (cgroup, container) = match process_info.name.as_str() {
+            "4" => (
+                format!("/system.slice/docker-{}.scope", "4".repeat(64)),
+                Container {
+                    id: "4".repeat(64),
+                    pod_uid: None,
+                },
+            ),
+            "5" => (
+                format!(
+                    "/kubepods/besteffort/pod{}/{}",
+                    "55555555-5555-5555-5555-555555555555",
+                    "5".repeat(64)
+                ),
+                Container {
+                    id: "5".repeat(64),
+                    pod_uid: Some(String::from("55555555-5555-5555-5555-555555555555")),
+                },
+            ),
+            _ => continue,
+        };
+        process_info.cgroup = cgroup;
+        process_info.container = Some(container);
+    }
+    open_sockets
+}
+
+pub fn get_no_open_sockets() -> OpenSockets {
+    OpenSockets {
+        sockets_to_procs: HashMap::new(),
+        connections: Vec::new(),
+        pids: HashSet::new(),
+    }
+}
+
+// the sockets are only there for the first poll, as if their connections closed right after it
+pub fn get_open_sockets_once() -> OpenSockets {
+    static POLLED: AtomicBool = AtomicBool::new(false);
+    if POLLED.swap(true, Ordering::SeqCst) {
+        get_no_open_sockets()
+    } else {
+        get_open_sockets()
+    }
+}
+
+// the connection of "1" on port 443 sends 1000 bytes and receives 500 after the first poll
+pub fn get_tcp_counters() -> Vec<SocketCounter> {
+    static POLLED: AtomicBool = AtomicBool::new(false);
+    let (bytes_sent, bytes_received) = if POLLED.swap(true, Ordering::SeqCst) {
+        (1500, 600)
+    } else {
+        (500, 100)
+    };
+    vec![SocketCounter {
+        connection: Connection::new(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 12345),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            443,
+            Protocol::Tcp,
+        ),
+        bytes_sent,
+        bytes_received,
+    }]
+}
+
+pub fn get_no_interface_counters() -> HashMap<String, InterfaceCounters> {
+    HashMap::new()
+}
+
+// on each poll, the 100 Mbit/s interface receives 1.25MB (10% of its link) in 1000 packets, sends
+// 250KB in 200 and has an error, while the loopback (of unknown speed) sends and receives 1KB
+pub fn get_interface_counters() -> HashMap<String, InterfaceCounters> {
+    static POLLS: AtomicU64 = AtomicU64::new(0);
+    let polls = POLLS.fetch_add(1, Ordering::SeqCst);
+    vec![
+        (
+            String::from("interface_name"),
+            InterfaceCounters {
+                bytes_received: 1_250_000 * polls,
+                bytes_sent: 250_000 * polls,
+                packets_received: 1000 * polls,
+                packets_sent: 200 * polls,
+                errors: polls,
+                drops: 0,
+                speed: Some(100),
+            },
+        ),
+        (
+            String::from("lo"),
+            InterfaceCounters {
+                bytes_received: 1000 * polls,
+                bytes_sent: 1000 * polls,
+                packets_received: 10 * polls,
+                packets_sent: 10 * polls,
+                ..Default::default()
+            },
+        ),
+    ]
+    .into_iter()
+    .collect()
+}
+
+// the kernel drops no packets until the second poll, then 3 more on each poll
+pub fn get_capture_drops() -> Box<GetCaptureDrops> {
+    let mut polls = 0;
+    Box::new(move || {
+        let drops = 3 * polls;
+        polls += 1;
+        vec![(String::from("interface_name"), drops)]
+            .into_iter()
+            .collect()
+    })
+}
+
+pub struct DelayedReader {
+    inner: Cursor<Vec<u8>>,
+    delay_at: u64,
+}
+
+impl DelayedReader {
+    pub fn new(bytes: Vec<u8>, delay_at: u64) -> Self {
+        DelayedReader {
+            inner: Cursor::new(bytes),
+            delay_at,
+        }
+    }
+}
+
+impl Read for DelayedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.inner.position() == self.delay_at {
+            // make it less likely to have a race condition with the display loop
+            thread::sleep(time::Duration::from_millis(500));
+        }
+        self.inner.read(buf)
     }
 }
 